@@ -0,0 +1,328 @@
+// word golf - turn one word into another, one letter at a time
+// a rust take on the wgolf C program, minus the fixed-size string pool
+use crate::gram::{Alphabet, Dict};
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, BinaryHeap, HashMap, HashSet, VecDeque};
+
+// which steps are allowed between two rungs of a ladder
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Moves {
+    pub substitute: bool, // head -> heal
+    pub insert: bool,     // heal -> heals
+    pub delete: bool,     // heals -> heal
+    pub anagram: bool,    // heal -> hale
+}
+
+impl Default for Moves {
+    // classic word golf, substitutions only
+    fn default() -> Self {
+        Moves {
+            substitute: true,
+            insert: false,
+            delete: false,
+            anagram: false,
+        }
+    }
+}
+
+impl Moves {
+    pub fn all() -> Self {
+        Moves {
+            substitute: true,
+            insert: true,
+            delete: true,
+            anagram: true,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Ladder {
+    moves: Moves,
+    alphabet: Alphabet,
+    // canonical forms of every word
+    words: HashSet<String>,
+    // every letter seen in words, used for substitutions and insertions
+    letters: BTreeSet<char>,
+    // signature -> words, used for anagram steps
+    anagrams: HashMap<String, Vec<String>>,
+}
+
+impl Ladder {
+    pub fn new(moves: Moves) -> Ladder {
+        Ladder::with_alphabet(moves, Alphabet::english())
+    }
+
+    pub fn with_alphabet(moves: Moves, alphabet: Alphabet) -> Ladder {
+        Ladder {
+            moves,
+            alphabet,
+            ..Default::default()
+        }
+    }
+
+    pub fn from_dict(dict: &Dict, moves: Moves) -> Ladder {
+        let mut ladder = Ladder::with_alphabet(moves, dict.alphabet().clone());
+        for word in dict.words() {
+            ladder.load(word);
+        }
+        ladder
+    }
+
+    pub fn load(&mut self, word: &str) {
+        let canonical = self.alphabet.canonicalize(word);
+        if canonical.is_empty() || self.words.contains(&canonical) {
+            return;
+        }
+        self.letters.extend(canonical.chars());
+        self.anagrams
+            .entry(self.alphabet.signature(&canonical))
+            .or_default()
+            .push(canonical.clone());
+        self.words.insert(canonical);
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        self.words.contains(&self.alphabet.canonicalize(word))
+    }
+
+    // every word reachable from word in a single allowed move, sorted
+    pub fn neighbors(&self, word: &str) -> Vec<String> {
+        let letters: Vec<char> = word.chars().collect();
+        let mut found = BTreeSet::new();
+        let mut check = |candidate: String| {
+            if candidate != word && self.words.contains(&candidate) {
+                found.insert(candidate);
+            }
+        };
+
+        if self.moves.substitute {
+            for i in 0..letters.len() {
+                for &ch in self.letters.iter().filter(|&&ch| ch != letters[i]) {
+                    let mut next = letters.clone();
+                    next[i] = ch;
+                    check(next.into_iter().collect());
+                }
+            }
+        }
+
+        if self.moves.insert {
+            for i in 0..=letters.len() {
+                for &ch in self.letters.iter() {
+                    let mut next = letters.clone();
+                    next.insert(i, ch);
+                    check(next.into_iter().collect());
+                }
+            }
+        }
+
+        if self.moves.delete {
+            for i in 0..letters.len() {
+                let mut next = letters.clone();
+                next.remove(i);
+                check(next.into_iter().collect());
+            }
+        }
+
+        if self.moves.anagram {
+            if let Some(words) = self.anagrams.get(&self.alphabet.signature(word)) {
+                for other in words {
+                    check(other.clone());
+                }
+            }
+        }
+
+        found.into_iter().collect()
+    }
+
+    // a shortest ladder from src to dest, found with A*
+    // returns None if either word isn't in the ladder or dest is unreachable
+    pub fn shortest(&self, src: &str, dest: &str) -> Option<Vec<String>> {
+        let (src, dest) = self.endpoints(src, dest)?;
+        let mut frontier = BinaryHeap::new();
+        let mut came_from: HashMap<String, String> = HashMap::new();
+        let mut cost: HashMap<String, usize> = HashMap::new();
+
+        cost.insert(src.clone(), 0);
+        frontier.push(Reverse((self.estimate(&src, &dest), 0, src.clone())));
+
+        while let Some(Reverse((_, steps, word))) = frontier.pop() {
+            if word == dest {
+                let mut path = vec![word];
+                while let Some(prev) = came_from.get(path.last().unwrap()) {
+                    path.push(prev.clone());
+                }
+                path.reverse();
+                return Some(path);
+            }
+            if steps > cost[&word] {
+                continue; // stale entry
+            }
+
+            for next in self.neighbors(&word) {
+                let next_steps = steps + 1;
+                if cost.get(&next).is_none_or(|&known| next_steps < known) {
+                    cost.insert(next.clone(), next_steps);
+                    came_from.insert(next.clone(), word.clone());
+                    let priority = next_steps + self.estimate(&next, &dest);
+                    frontier.push(Reverse((priority, next_steps, next)));
+                }
+            }
+        }
+        None
+    }
+
+    // every shortest ladder from src to dest, found with a layered BFS
+    pub fn all_shortest(&self, src: &str, dest: &str) -> Vec<Vec<String>> {
+        let (src, dest) = match self.endpoints(src, dest) {
+            Some(endpoints) => endpoints,
+            None => return Vec::new(),
+        };
+
+        // every word on a shortest path to a key, one layer back
+        let mut parents: HashMap<String, Vec<String>> = HashMap::new();
+        let mut depth: HashMap<String, usize> = HashMap::new();
+        let mut queue = VecDeque::new();
+        depth.insert(src.clone(), 0);
+        queue.push_back(src.clone());
+
+        while let Some(word) = queue.pop_front() {
+            let d = depth[&word];
+            if depth.get(&dest).is_some_and(|&found| d >= found) {
+                break;
+            }
+            for next in self.neighbors(&word) {
+                match depth.get(&next) {
+                    None => {
+                        depth.insert(next.clone(), d + 1);
+                        parents.insert(next.clone(), vec![word.clone()]);
+                        queue.push_back(next);
+                    }
+                    Some(&nd) if nd == d + 1 => parents.get_mut(&next).unwrap().push(word.clone()),
+                    _ => {}
+                }
+            }
+        }
+
+        if !depth.contains_key(&dest) {
+            return Vec::new();
+        }
+
+        let mut ladders = Vec::new();
+        Ladder::unwind(&parents, &src, vec![dest], &mut ladders);
+        ladders.sort();
+        ladders
+    }
+
+    // walks parents back from the last word of path to src
+    fn unwind(parents: &HashMap<String, Vec<String>>, src: &str, path: Vec<String>, out: &mut Vec<Vec<String>>) {
+        let last = path.last().unwrap();
+        if last == src {
+            out.push(path.into_iter().rev().collect());
+            return;
+        }
+        for prev in &parents[last] {
+            let mut longer = path.clone();
+            longer.push(prev.clone());
+            Ladder::unwind(parents, src, longer, out);
+        }
+    }
+
+    fn endpoints(&self, src: &str, dest: &str) -> Option<(String, String)> {
+        let src = self.alphabet.canonicalize(src);
+        let dest = self.alphabet.canonicalize(dest);
+        match self.words.contains(&src) && self.words.contains(&dest) {
+            true => Some((src, dest)),
+            false => None,
+        }
+    }
+
+    // a lower bound on the steps between two words, for A*
+    fn estimate(&self, from: &str, to: &str) -> usize {
+        // no move fixes more than one surplus and one missing letter at a time
+        let mut counts: HashMap<char, isize> = HashMap::new();
+        for ch in from.chars() {
+            *counts.entry(ch).or_default() += 1;
+        }
+        for ch in to.chars() {
+            *counts.entry(ch).or_default() -= 1;
+        }
+        let surplus: isize = counts.values().filter(|&&n| n > 0).sum();
+        let missing: isize = -counts.values().filter(|&&n| n < 0).sum::<isize>();
+        let letters = surplus.max(missing) as usize;
+
+        // with only substitutions, every mismatched position costs a step
+        if self.moves == Moves::default() && from.chars().count() == to.chars().count() {
+            let mismatched = from.chars().zip(to.chars()).filter(|(a, b)| a != b).count();
+            return letters.max(mismatched);
+        }
+        letters
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ladder(moves: Moves, words: &[&str]) -> Ladder {
+        let mut ladder = Ladder::new(moves);
+        for word in words {
+            ladder.load(word);
+        }
+        ladder
+    }
+
+    #[test]
+    fn neighbors_works() {
+        let l = ladder(Moves::default(), &["head", "heal", "heals", "hale", "dead"]);
+        assert_eq!(l.neighbors("head"), vec!["dead", "heal"]);
+
+        let l = ladder(Moves::all(), &["head", "heal", "heals", "hale", "dead"]);
+        assert_eq!(l.neighbors("heal"), vec!["hale", "head", "heals"]);
+        assert_eq!(l.neighbors("heals"), vec!["heal"]);
+    }
+
+    #[test]
+    fn shortest_works() {
+        let words = ["head", "heal", "teal", "tell", "tall", "tail", "hell", "hall", "hail"];
+        let l = ladder(Moves::default(), &words);
+        let path = l.shortest("HEAD", "tail").unwrap();
+        assert_eq!(path.len(), 6);
+        assert_eq!(path.first().unwrap(), "head");
+        assert_eq!(path.last().unwrap(), "tail");
+        assert_eq!(l.shortest("head", "head"), Some(vec!["head".to_owned()]));
+        assert_eq!(l.shortest("head", "zzzz"), None);
+    }
+
+    #[test]
+    fn shortest_with_insert_delete_and_anagram() {
+        let words = ["lewis", "wiles", "wile", "wide"];
+        assert_eq!(ladder(Moves::default(), &words).shortest("lewis", "wide"), None);
+        let path = ladder(Moves::all(), &words).shortest("lewis", "wide").unwrap();
+        assert_eq!(path, vec!["lewis", "wiles", "wile", "wide"]);
+    }
+
+    #[test]
+    fn shortest_from_dict_keeps_locale() {
+        let mut dict = Dict::with_alphabet(Alphabet::spanish());
+        for word in ["año", "paño", "pañi", "pani"] {
+            dict.load(word);
+        }
+        let l = Ladder::from_dict(&dict, Moves::all());
+        assert_eq!(l.shortest("año", "pañi").unwrap(), vec!["año", "paño", "pañi"]);
+        assert!(!l.contains("ano"));
+    }
+
+    #[test]
+    fn all_shortest_works() {
+        let words = ["cat", "cot", "cog", "dog", "cag", "dot"];
+        let l = ladder(Moves::default(), &words);
+        let ladders = l.all_shortest("cat", "dog");
+        assert_eq!(ladders, vec![
+            vec!["cat", "cag", "cog", "dog"],
+            vec!["cat", "cot", "cog", "dog"],
+            vec!["cat", "cot", "dot", "dog"],
+        ]);
+        assert!(l.all_shortest("cat", "cow").is_empty());
+    }
+}
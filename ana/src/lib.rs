@@ -1,42 +1,241 @@
+pub mod golf;
+pub mod session;
+
 pub mod gram {
     use std::path::Path;
     use std::fs::File;
     use std::io::{self, BufRead};
     use itertools::Itertools;
-    
-    #[derive(Debug)]
+    use std::cmp::Reverse;
+    use std::collections::HashMap;
+    use unicode_normalization::UnicodeNormalization;
+    use unicode_normalization::char::is_combining_mark;
+
+    const ENGLISH: &str = "abcdefghijklmnopqrstuvwxyz";
+    // how many candidate words a multi-word search may try before giving up with what it has,
+    // the limit on results alone doesn't stop a search that finds little from running for seconds
+    pub const PHRASE_BUDGET: usize = 2_000_000;
+
+    // the letters of a locale, in order, plus any variant forms folded onto them
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Alphabet {
+        letters: Vec<char>,
+        folds: Vec<(char, char)>,
+    }
+
+    impl Default for Alphabet {
+        fn default() -> Self {
+            Alphabet::english()
+        }
+    }
+
+    impl Alphabet {
+        pub fn new(letters: &str, folds: &[(char, char)]) -> Alphabet {
+            Alphabet {
+                letters: letters.chars().collect(),
+                folds: folds.to_vec(),
+            }
+        }
+
+        pub fn english() -> Alphabet {
+            Alphabet::new(ENGLISH, &[])
+        }
+
+        // umlauts and eszett are letters of their own, not a/o/u/ss with decoration
+        pub fn german() -> Alphabet {
+            Alphabet::new("abcdefghijklmnopqrstuvwxyzäöüß", &[])
+        }
+
+        pub fn spanish() -> Alphabet {
+            Alphabet::new("abcdefghijklmnñopqrstuvwxyz", &[])
+        }
+
+        pub fn greek() -> Alphabet {
+            Alphabet::new("αβγδεζηθικλμνξοπρστυφχψω", &[('ς', 'σ')])
+        }
+
+        pub fn hebrew() -> Alphabet {
+            Alphabet::new(
+                "אבגדהוזחטיכלמנסעפצקרשת",
+                &[('ך', 'כ'), ('ם', 'מ'), ('ן', 'נ'), ('ף', 'פ'), ('ץ', 'צ')],
+            )
+        }
+
+        // EX: for_locale("de_DE.UTF-8") -> Some(Alphabet::german())
+        pub fn for_locale(locale: &str) -> Option<Alphabet> {
+            let lang = locale.split(['_', '-', '.']).next()?;
+            match lang.to_lowercase().as_str() {
+                "en" => Some(Alphabet::english()),
+                "de" => Some(Alphabet::german()),
+                "es" => Some(Alphabet::spanish()),
+                "el" | "gr" => Some(Alphabet::greek()),
+                "he" | "iw" => Some(Alphabet::hebrew()),
+                _ => None,
+            }
+        }
+
+        fn fold(&self, ch: char) -> char {
+            match self.folds.iter().find(|(from, _)| *from == ch) {
+                Some(&(_, to)) => to,
+                None => ch,
+            }
+        }
+
+        pub fn contains(&self, ch: char) -> bool {
+            self.letters.contains(&self.fold(ch))
+        }
+
+        // if not in the alphabet, returns 0
+        pub fn ord(&self, ch: char) -> usize {
+            let ch = self.fold(ch);
+            self.letters.iter().position(|&x| x == ch).unwrap_or_default()
+        }
+
+        pub fn str_ord(&self, s: &str) -> usize {
+            s.chars().map(|ch| self.ord(ch)).sum()
+        }
+
+        // case folds, strips diacritics that aren't part of a letter of this alphabet
+        // and drops anything that isn't a letter or digit
+        // composes first, so an n followed by a combining tilde is still ñ
+        // EX: "Café-Noir's" -> "cafenoirs", but "Año" -> "año" in spanish
+        pub fn canonicalize(&self, word: &str) -> String {
+            let mut res = String::new();
+            for ch in word.chars().flat_map(char::to_lowercase).nfc().map(|ch| self.fold(ch)) {
+                if self.letters.contains(&ch) {
+                    res.push(ch);
+                    continue;
+                }
+                for base in Some(ch).into_iter().nfd().filter(|&c| !is_combining_mark(c)) {
+                    let base = self.fold(base);
+                    if base.is_alphanumeric() {
+                        res.push(base);
+                    }
+                }
+            }
+            res
+        }
+
+        // the canonical letters of word in a fixed order, equal for all anagrams
+        pub fn signature(&self, word: &str) -> String {
+            self.canonicalize(word).chars().sorted().rev().collect()
+        }
+    }
+
+    #[derive(Debug, Default)]
     pub struct Dict {
-        // stored in alphabetical order
+        alphabet: Alphabet,
+        // stored in alphabetical order, as (surface form, signature)
         entries: Vec<(String, String)>
     }
 
     impl Dict {
         pub fn new() -> Dict {
+            Dict::with_alphabet(Alphabet::english())
+        }
+
+        pub fn with_alphabet(alphabet: Alphabet) -> Dict {
             Dict {
+                alphabet,
                 entries: Vec::new(),
             }
         }
 
+        pub fn alphabet(&self) -> &Alphabet {
+            &self.alphabet
+        }
+
         // stores a copy of word in vec
         pub fn load(&mut self, word: &str) {
             let entry = (
                 word.to_string(),
-                word.chars().sorted().rev().collect(),
+                self.alphabet.signature(word),
             );
             self.entries.push(entry);
         }
 
+        // returns the surface forms of every entry with the same letters as input,
+        // excluding those that only differ from input by case, accents or punctuation
         pub fn anagrams(&self, input: &str) -> Vec<&str> {
             let mut res: Vec<&str> = vec![];
-            let sorted_input = input.chars().sorted().rev().collect::<String>();
+            let canonical_input = self.alphabet.canonicalize(input);
+            let sorted_input = self.alphabet.signature(input);
             for (entry, ordered) in self.entries.iter() {
-                if  sorted_input == *ordered && input != entry {
+                if sorted_input == *ordered && canonical_input != self.alphabet.canonicalize(entry) {
                     res.push(entry);
                 }
             }
             res
         }
 
+        // surface forms of every entry, in load order
+        pub fn words(&self) -> impl Iterator<Item = &str> {
+            self.entries.iter().map(|(word, _)| word.as_str())
+        }
+
+        pub fn len(&self) -> usize {
+            self.entries.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.entries.is_empty()
+        }
+
+        // every entry that can be spelled with the letters of input, longest first
+        pub fn constructible(&self, input: &str) -> Vec<&str> {
+            let letters = self.alphabet.signature(input);
+            let mut res: Vec<&str> = self.entries.iter()
+                .filter(|(_, ordered)| !ordered.is_empty() && subtract(&letters, ordered).is_some())
+                .map(|(entry, _)| entry.as_str())
+                .collect();
+            res.sort_by_key(|entry| Reverse(entry.chars().count()));
+            res
+        }
+
+        // phrases of up to max_words entries that use up exactly the letters of input
+        // stops after limit phrases or PHRASE_BUDGET tries, the search space gets big fast
+        pub fn multi_word_anagrams(&self, input: &str, max_words: usize, limit: usize) -> Vec<Vec<&str>> {
+            let letters = self.alphabet.signature(input);
+            let canonical_input = self.alphabet.canonicalize(input);
+
+            // entries that fit in input, grouped by signature so each is only searched once
+            let mut groups: Vec<(&str, Vec<&str>)> = vec![];
+            let mut index: HashMap<&str, usize> = HashMap::new();
+            for (entry, ordered) in self.entries.iter() {
+                if ordered.is_empty() || subtract(&letters, ordered).is_none() {
+                    continue;
+                }
+                match index.get(ordered.as_str()) {
+                    Some(&i) => groups[i].1.push(entry),
+                    None => {
+                        index.insert(ordered, groups.len());
+                        groups.push((ordered, vec![entry]));
+                    }
+                }
+            }
+
+            let mut found = vec![];
+            let mut budget = PHRASE_BUDGET;
+            find_phrases(&groups, &index, &letters, 0, max_words, &mut vec![], &mut found, limit, &mut budget);
+
+            let mut res = vec![];
+            for phrase in found {
+                let spellings = phrase.iter()
+                    .map(|&i| groups[i].1.iter().copied())
+                    .multi_cartesian_product();
+                for words in spellings {
+                    if words.len() == 1 && self.alphabet.canonicalize(words[0]) == canonical_input {
+                        continue;
+                    }
+                    res.push(words);
+                    if res.len() == limit {
+                        return res;
+                    }
+                }
+            }
+            res
+        }
+
         // returns an iterator over the lines of the filename
         fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
         where P: AsRef<Path> {
@@ -55,19 +254,82 @@ pub mod gram {
         }
     }
 
-    // if not alphabetical, returns 0
-    fn ord(ch: char) -> usize {
-        let alphabet = ['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p',
-        'q', 'r', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z'];
-        match alphabet.iter().position(|&x| x == ch) {
-            Some(i) => i,
-            _ => 0
+    // removes the letters of part from whole, both signatures
+    // returns None if part has a letter that whole doesn't
+    fn subtract(whole: &str, part: &str) -> Option<String> {
+        let mut rest = String::with_capacity(whole.len());
+        let mut part = part.chars().peekable();
+        for ch in whole.chars() {
+            match part.peek() {
+                Some(&p) if p == ch => { part.next(); },
+                // signatures are in descending order, so p would have been seen already
+                Some(&p) if p > ch => return None,
+                _ => rest.push(ch),
+            }
+        }
+        match part.next() {
+            None => Some(rest),
+            Some(_) => None,
+        }
+    }
+
+    // depth-first search over signature groups, in non-decreasing order to skip permutations
+    // each group tried spends one from budget, and the search stops when it runs out
+    #[allow(clippy::too_many_arguments)]
+    fn find_phrases(groups: &[(&str, Vec<&str>)], index: &HashMap<&str, usize>, letters: &str, start: usize,
+                    words_left: usize, chosen: &mut Vec<usize>, found: &mut Vec<Vec<usize>>, limit: usize,
+                    budget: &mut usize) {
+        if found.len() >= limit {
+            return;
+        }
+        if letters.is_empty() {
+            found.push(chosen.clone());
+            return;
+        }
+        if words_left == 0 {
+            return;
+        }
+        if words_left == 1 {
+            // the last word has to be exactly what's left
+            if let Some(&i) = index.get(letters) {
+                if i >= start {
+                    chosen.push(i);
+                    found.push(chosen.clone());
+                    chosen.pop();
+                }
+            }
+            return;
+        }
+
+        for (i, (ordered, _)) in groups.iter().enumerate().skip(start) {
+            if *budget == 0 {
+                return;
+            }
+            *budget -= 1;
+            if let Some(rest) = subtract(letters, ordered) {
+                chosen.push(i);
+                find_phrases(groups, index, &rest, i, words_left - 1, chosen, found, limit, budget);
+                chosen.pop();
+                if found.len() >= limit {
+                    return;
+                }
+            }
         }
     }
 
+    // canonicalize using the english alphabet
+    pub fn canonicalize(word: &str) -> String {
+        Alphabet::english().canonicalize(word)
+    }
+
+    // signature using the english alphabet
+    pub fn signature(word: &str) -> String {
+        Alphabet::english().signature(word)
+    }
+
     // ascii 4ever
     pub fn str_ord(s: &str) -> usize {
-        return s.chars().map(|ch| ord(ch)).sum()
+        Alphabet::english().str_ord(s)
     }
 
     // returns a new string word without the first occurrence of letter
@@ -132,7 +394,7 @@ pub mod gram {
 
     // TODO: two heap allocations just for to check for anagrams lol
     pub fn are_anagrams(a: &str, b: &str) -> bool {
-        a.chars().sorted().rev().collect::<String>() == b.chars().sorted().rev().collect::<String>()
+        signature(a) == signature(b)
     }
 
     pub fn single_word_anagrams<'a>(words: &[&str], dict: &'a [&str]) -> Vec<&'a str> {
@@ -159,6 +421,92 @@ mod tests {
         assert_eq!(str_ord("wiles"), 63);
     }
 
+    #[test]
+    fn locale_str_ord_works() {
+        assert_eq!(Alphabet::spanish().str_ord("ñ"), 14);
+        assert_eq!(Alphabet::spanish().str_ord("o"), 15);
+        assert_eq!(Alphabet::german().str_ord("ß"), 29);
+        assert_eq!(Alphabet::greek().str_ord("αβγ"), 3);
+        assert_eq!(Alphabet::greek().ord('ς'), Alphabet::greek().ord('σ'));
+        assert_eq!(Alphabet::hebrew().ord('ם'), Alphabet::hebrew().ord('מ'));
+        assert_eq!(str_ord("ñ"), 0);
+    }
+
+    #[test]
+    fn locale_canonicalize_works() {
+        assert_eq!(Alphabet::spanish().canonicalize("Año"), "año");
+        assert_eq!(Alphabet::english().canonicalize("Año"), "ano");
+        assert_eq!(Alphabet::german().canonicalize("Straße"), "straße");
+        assert_eq!(Alphabet::german().canonicalize("Öl"), "öl");
+        assert_eq!(Alphabet::greek().canonicalize("Λόγος"), "λογοσ");
+        assert_eq!(Alphabet::hebrew().canonicalize("שָׁלוֹם"), "שלומ");
+        // decomposed, as word lists saved on macOS often are
+        assert_eq!(Alphabet::spanish().canonicalize("An\u{303}o"), "año");
+        assert_eq!(Alphabet::german().canonicalize("O\u{308}l"), "öl");
+        assert_eq!(Alphabet::english().canonicalize("an\u{303}o"), "ano");
+    }
+
+    #[test]
+    fn locale_dict_anagrams_works() {
+        let mut dict = Dict::with_alphabet(Alphabet::spanish());
+        for word in ["año", "ano", "oña", "ñoa"] {
+            dict.load(word);
+        }
+        assert_eq!(dict.anagrams("Año"), vec!["oña", "ñoa"]);
+        assert_eq!(dict.anagrams("An\u{303}o"), vec!["oña", "ñoa"]);
+        let mut nfd = Dict::with_alphabet(Alphabet::spanish());
+        nfd.load("an\u{303}o");
+        assert_eq!(nfd.anagrams("oña"), vec!["an\u{303}o"]);
+
+        let mut dict = Dict::with_alphabet(Alphabet::greek());
+        for word in ["ώρες", "έρως", "κόσμος"] {
+            dict.load(word);
+        }
+        assert_eq!(dict.anagrams("Έρως"), vec!["ώρες"]);
+        assert_eq!(Alphabet::for_locale("el_GR.UTF-8"), Some(Alphabet::greek()));
+        assert_eq!(Alphabet::for_locale("xx"), None);
+    }
+
+    #[test]
+    fn constructible_works() {
+        let mut dict = Dict::new();
+        for word in ["a", "cool", "loco", "Haman", "coils", "is", "hams"] {
+            dict.load(word);
+        }
+        assert_eq!(dict.constructible("haman is cool"), vec!["Haman", "coils", "cool", "loco", "hams", "is", "a"]);
+        assert_eq!(dict.constructible("xyz"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn multi_word_anagrams_works() {
+        let mut dict = Dict::new();
+        for word in ["dirty", "room", "moor", "dormitory", "dirt", "y", "toy", "dim", "ror"] {
+            dict.load(word);
+        }
+        let res = dict.multi_word_anagrams("dormitory", 2, 10);
+        assert_eq!(res, vec![vec!["dirty", "room"], vec!["dirty", "moor"]]);
+
+        let res = dict.multi_word_anagrams("dirty room", 3, 10);
+        assert!(res.contains(&vec!["dormitory"]));
+        assert!(res.contains(&vec!["room", "dirt", "y"]) || res.contains(&vec!["dirt", "room", "y"]));
+        assert_eq!(dict.multi_word_anagrams("dirty room", 3, 2).len(), 2);
+        assert!(dict.multi_word_anagrams("qqq", 3, 10).is_empty());
+    }
+
+    #[test]
+    fn multi_word_anagrams_gives_up() {
+        // every pair of letters but no z, so nothing is ever found and only the budget ends the search
+        let mut dict = Dict::new();
+        let letters = "abcdefghijklmnopqrstuvwxy";
+        for a in letters.chars() {
+            for b in letters.chars() {
+                dict.load(&format!("{}{}", a, b));
+            }
+        }
+        let input = format!("{}{}z", letters, letters);
+        assert!(dict.multi_word_anagrams(&input, 26, 10).is_empty());
+    }
+
     #[test]
     fn single_word_anagrams_works() {
         let words = ["lewis"];
@@ -168,6 +516,33 @@ mod tests {
         assert_eq!(res[0], "wiles");
     }
 
+    #[test]
+    fn canonicalize_works() {
+        assert_eq!(canonicalize("Lewis"), "lewis");
+        assert_eq!(canonicalize("Café"), "cafe");
+        assert_eq!(canonicalize("don't stop"), "dontstop");
+        assert_eq!(canonicalize("ÉMILE"), "emile");
+    }
+
+    #[test]
+    fn are_anagrams_ignores_case_accents_and_punctuation() {
+        assert!(are_anagrams("Lewis", "wiles"));
+        assert!(are_anagrams("Éliot", "toile"));
+        assert!(are_anagrams("dormitory", "dirty room!"));
+        assert!(!are_anagrams("lewis", "willes"));
+    }
+
+    #[test]
+    fn dict_anagrams_returns_surface_forms() {
+        let mut dict = Dict::new();
+        for word in ["Lewis", "wiles", "Wile's", "élan", "Lena", "lane"] {
+            dict.load(word);
+        }
+        assert_eq!(dict.anagrams("lewis"), vec!["wiles", "Wile's"]);
+        assert_eq!(dict.anagrams("WILES"), vec!["Lewis"]);
+        assert_eq!(dict.anagrams("lean"), vec!["élan", "Lena", "lane"]);
+    }
+
     #[test]
     fn without_letter_works() {
         assert_eq!(without_letter("word", 'w'), Some("ord".to_owned()));
@@ -1,5 +1,9 @@
+use ana::gram::{Alphabet, Dict};
+use ana::session::{Format, Session};
 use cursive::{
-    event::Event,
+    event::{Event, Key},
+    menu,
+    theme::Theme,
     traits::*,
     views::{Dialog, EditView, LinearLayout, ScrollView, SelectView, TextView},
     Cursive,
@@ -7,6 +11,45 @@ use cursive::{
 use log::LevelFilter;
 use std::process;
 
+const DEFAULT_DICT: &str = "res/words.txt";
+// multi-word searches blow up quickly, so both are capped
+const MAX_WORDS: usize = 3;
+const MAX_RESULTS: usize = 100;
+const HELP: &str = "type letters into the box and press enter to remove them from the selected sentence
+
+F1      open the menu
+Esc     quit
+
+modes:
+  single         one-word anagrams of the selection
+  multi-word     phrases of up to three words using all of its letters
+  constructible  words that can be spelled with its letters";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Single,
+    MultiWord,
+    Constructible,
+}
+
+impl Mode {
+    fn label(&self) -> &'static str {
+        match self {
+            Mode::Single => "single",
+            Mode::MultiWord => "multi-word",
+            Mode::Constructible => "constructible",
+        }
+    }
+}
+
+// everything the callbacks need, stored as the cursive user data
+struct State {
+    dict: Dict,
+    session: Session,
+    mode: Mode,
+    results: usize,
+}
+
 fn usage_and_exit() {
     println!("program: ana \"sentence\"");
     process::exit(1);
@@ -25,27 +68,53 @@ fn main() {
     }
 
     // load dict from pre-defined filepath
-    let mut dict = ana::gram::Dict::new();
-    dict.load_from_file("res/words.txt");
+    let mut dict = Dict::new();
+    dict.load_from_file(DEFAULT_DICT);
 
     let input: &str = args[1].trim();
     let mut siv = cursive::default();
-    siv.set_theme(cursive::theme::Theme::default().with(|theme| {
-        use cursive::theme::{BaseColor::*, Color::*, PaletteColor::*};
-        theme.shadow = false;
-        theme.palette[Background] = TerminalDefault;
-        theme.palette[Primary] = Dark(Black);
-        theme.palette[Secondary] = Dark(Black);
-        theme.palette[Tertiary] = Dark(Black);
-        theme.palette[TitlePrimary] = Dark(Red);
-        theme.palette[TitleSecondary] = Dark(Black);
-        theme.palette[Highlight] = Dark(Red);
-        theme.palette[HighlightInactive] = Dark(Black);
-    }));
+    siv.set_theme(ana_theme());
 
-    siv.add_global_callback(Event::Key(cursive::event::Key::Esc), |s| s.quit());
+    siv.add_global_callback(Event::Key(Key::Esc), |s| s.quit());
+    siv.add_global_callback(Key::F1, |s| s.select_menubar());
+    siv.menubar()
+        .add_subtree(
+            "File",
+            menu::Tree::new()
+                .leaf("Load dictionary", load_dict_dialog)
+                .subtree(
+                    "Export session",
+                    menu::Tree::new()
+                        .leaf("Markdown", |s| export_dialog(s, Format::Markdown))
+                        .leaf("JSON", |s| export_dialog(s, Format::Json)),
+                )
+                .delimiter()
+                .leaf("Quit", |s| s.quit()),
+        )
+        .add_subtree(
+            "Mode",
+            menu::Tree::new()
+                .leaf("Single word", |s| set_mode(s, Mode::Single))
+                .leaf("Multi-word", |s| set_mode(s, Mode::MultiWord))
+                .leaf("Constructible", |s| set_mode(s, Mode::Constructible)),
+        )
+        .add_subtree(
+            "Theme",
+            menu::Tree::new()
+                .leaf("ana", |s| s.set_theme(ana_theme()))
+                .leaf("classic", |s| s.set_theme(Theme::default()))
+                .leaf("terminal", |s| s.set_theme(terminal_theme())),
+        )
+        .add_leaf("Help", |s| s.add_layer(Dialog::info(HELP).title("help")));
+    siv.set_autohide_menu(true);
 
     let input = input.to_string();
+    siv.set_user_data(State {
+        dict,
+        session: Session::new(&input),
+        mode: Mode::Single,
+        results: 0,
+    });
     let input_box = EditView::new()
         .on_submit(handle_submit)
         .with_name("input_box")
@@ -75,31 +144,227 @@ fn main() {
             ),
     );
 
-    run_single_anagrams(&mut siv, &dict);
+    search(&mut siv);
     siv.run();
 }
 
-fn run_single_anagrams(siv: &mut Cursive, dict: &ana::gram::Dict) {
+fn ana_theme() -> Theme {
+    Theme::default().with(|theme| {
+        use cursive::theme::{BaseColor::*, Color::*, PaletteColor::*};
+        theme.shadow = false;
+        theme.palette[Background] = TerminalDefault;
+        theme.palette[Primary] = Dark(Black);
+        theme.palette[Secondary] = Dark(Black);
+        theme.palette[Tertiary] = Dark(Black);
+        theme.palette[TitlePrimary] = Dark(Red);
+        theme.palette[TitleSecondary] = Dark(Black);
+        theme.palette[Highlight] = Dark(Red);
+        theme.palette[HighlightInactive] = Dark(Black);
+    })
+}
+
+// uses the terminal's own colors wherever it can
+fn terminal_theme() -> Theme {
+    Theme::default().with(|theme| {
+        use cursive::theme::{BaseColor::*, Color::*, PaletteColor::*};
+        theme.shadow = false;
+        theme.palette[Background] = TerminalDefault;
+        theme.palette[View] = TerminalDefault;
+        theme.palette[Primary] = TerminalDefault;
+        theme.palette[Secondary] = TerminalDefault;
+        theme.palette[Tertiary] = TerminalDefault;
+        theme.palette[TitlePrimary] = TerminalDefault;
+        theme.palette[TitleSecondary] = TerminalDefault;
+        theme.palette[Highlight] = Dark(White);
+        theme.palette[HighlightInactive] = Light(Black);
+    })
+}
+
+// runs the current mode on the selected sentence and lists what it finds
+fn search(siv: &mut Cursive) {
     let mut results_list = siv.find_name::<SelectView<String>>("results").unwrap();
-    let input = results_list.selection().unwrap();
+    let input = match results_list.selection() {
+        Some(input) => input,
+        None => return,
+    };
+
+    let (found, items) = siv
+        .with_user_data(|state: &mut State| {
+            let found: Vec<String> = match state.mode {
+                Mode::Single => state.dict.anagrams(&input).into_iter().map(String::from).collect(),
+                Mode::MultiWord => state
+                    .dict
+                    .multi_word_anagrams(&input, MAX_WORDS, MAX_RESULTS)
+                    .into_iter()
+                    .map(|words| words.join(" "))
+                    .collect(),
+                Mode::Constructible => state
+                    .dict
+                    .constructible(&input)
+                    .into_iter()
+                    .take(MAX_RESULTS)
+                    .map(String::from)
+                    .collect(),
+            };
+            if state.mode != Mode::Constructible {
+                for anagram in &found {
+                    state.session.add_anagram(anagram);
+                }
+            }
+            state.results = found.len();
+            (found, session_items(&state.session))
+        })
+        .unwrap_or_default();
+
+    // start over from the sentence and its steps, so results don't pile up under the last ones
+    results_list.clear();
+    let mut selected = None;
+    for (label, value) in items {
+        if selected.is_none() && value == *input {
+            selected = Some(results_list.len());
+        }
+        results_list.add_item(label, value);
+    }
+    // the selection was one of the results being replaced, keep it to search from again
+    let selected = selected.unwrap_or_else(|| {
+        results_list.add_item(input.to_string(), input.to_string());
+        results_list.len() - 1
+    });
+    for result in found {
+        results_list.add_item(result.clone(), result);
+    }
+    // nothing listens for selection changes, so the callback has nothing to do
+    let _ = results_list.set_selection(selected);
+    update_status(siv);
+}
 
-    let anagrams = dict.anagrams(&input);
-    for anagram in anagrams {
-        results_list.add_item(anagram.to_string(), anagram.to_string());
+// the sentence and every step taken from it, labelled as handle_submit lists them
+fn session_items(session: &Session) -> Vec<(String, String)> {
+    let mut items = vec![(session.sentence.clone(), session.sentence.clone())];
+    for step in &session.steps {
+        items.push((format!("{} -> {}", step.letters, step.remaining.to_uppercase()), step.remaining.clone()));
     }
+    items
+}
+
+fn update_status(siv: &mut Cursive) {
+    let status = siv
+        .with_user_data(|state: &mut State| {
+            let remaining = state.dict.alphabet().canonicalize(state.session.remaining());
+            format!(
+                "{} | dict: {} words | remaining: {} letters | results: {}",
+                state.mode.label(),
+                state.dict.len(),
+                remaining.chars().count(),
+                state.results
+            )
+        })
+        .unwrap_or_default();
+    siv.call_on_name("status_bar", |view: &mut TextView| view.set_content(status));
+}
+
+fn set_mode(siv: &mut Cursive, mode: Mode) {
+    siv.with_user_data(|state: &mut State| state.mode = mode);
+    search(siv);
 }
 
 fn handle_submit(siv: &mut Cursive, text: &str) {
     let mut results_list = siv.find_name::<SelectView<String>>("results").unwrap();
     let input = results_list.selection().unwrap();
-    let lowercase = input.to_ascii_lowercase();
-    let sentence: Vec<&str> = lowercase.split(' ').collect();
-    let letters: Vec<char> = text.chars().collect();
-    let result: String = ana::gram::remove_letters_from_sentence(&sentence[0..], &letters)
-        .join(" ")
-        .to_ascii_uppercase();
+    let alphabet = siv
+        .with_user_data(|state: &mut State| state.dict.alphabet().clone())
+        .unwrap_or_default();
+    let canonical: Vec<String> = input
+        .split_whitespace()
+        .map(|word| alphabet.canonicalize(word))
+        .filter(|word| !word.is_empty())
+        .collect();
+    let sentence: Vec<&str> = canonical.iter().map(String::as_str).collect();
+    let letters: Vec<char> = alphabet.canonicalize(text).chars().collect();
+    let remaining = ana::gram::remove_letters_from_sentence(&sentence, &letters);
     // TODO: show each letter of text in a different color, if its letters were used in the sentence
-    if result != lowercase {
-        results_list.add_item(text.to_string() + " -> " + &result, result);
+    if remaining != canonical {
+        // the canonical letters go on, only the label is uppercased, since that can change them (ß -> SS)
+        let result = remaining.join(" ");
+        siv.with_user_data(|state: &mut State| state.session.push_step(&input, text, &result));
+        results_list.add_item(text.to_string() + " -> " + &result.to_uppercase(), result);
+        update_status(siv);
     }
 }
+
+fn load_dict_dialog(siv: &mut Cursive) {
+    siv.add_layer(
+        Dialog::new()
+            .title("load dictionary")
+            .content(
+                LinearLayout::vertical()
+                    .child(TextView::new("path"))
+                    .child(EditView::new().content(DEFAULT_DICT).with_name("dict_path").fixed_width(40))
+                    .child(TextView::new("locale (en, de, es, el, he)"))
+                    .child(EditView::new().content("en").with_name("dict_locale").fixed_width(40)),
+            )
+            .button("Load", |s| {
+                let path = s
+                    .call_on_name("dict_path", |view: &mut EditView| view.get_content())
+                    .unwrap();
+                let locale = s
+                    .call_on_name("dict_locale", |view: &mut EditView| view.get_content())
+                    .unwrap();
+                load_dict(s, &path, &locale);
+            })
+            .dismiss_button("Cancel"),
+    );
+}
+
+fn load_dict(siv: &mut Cursive, path: &str, locale: &str) {
+    let alphabet = match Alphabet::for_locale(locale) {
+        Some(alphabet) => alphabet,
+        None => {
+            siv.add_layer(Dialog::info(format!("unknown locale '{}'", locale)));
+            return;
+        }
+    };
+
+    let mut dict = Dict::with_alphabet(alphabet);
+    dict.load_from_file(path);
+    if dict.is_empty() {
+        siv.add_layer(Dialog::info(format!("no words found in '{}'", path)));
+        return;
+    }
+
+    siv.pop_layer();
+    siv.with_user_data(|state: &mut State| state.dict = dict);
+    search(siv);
+}
+
+fn export_dialog(siv: &mut Cursive, format: Format) {
+    let default_path = format!("ana-session.{}", format.extension());
+    siv.add_layer(
+        Dialog::new()
+            .title("export session")
+            .content(
+                EditView::new()
+                    .content(default_path)
+                    .on_submit(move |s, path| export_session(s, path, format))
+                    .with_name("export_path")
+                    .fixed_width(40),
+            )
+            .button("Save", move |s| {
+                let path = s
+                    .call_on_name("export_path", |view: &mut EditView| view.get_content())
+                    .unwrap();
+                export_session(s, &path, format);
+            })
+            .dismiss_button("Cancel"),
+    );
+}
+
+fn export_session(siv: &mut Cursive, path: &str, format: Format) {
+    siv.pop_layer();
+    let message = match siv.with_user_data(|state: &mut State| state.session.export(path, format)) {
+        Some(Ok(())) => format!("exported to {}", path),
+        Some(Err(err)) => format!("export failed: {}", err),
+        None => "nothing to export".to_string(),
+    };
+    siv.add_layer(Dialog::info(message));
+}
@@ -0,0 +1,163 @@
+// a record of everything done to a sentence, for sharing findings
+use serde::Serialize;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Markdown,
+    Json,
+}
+
+impl Format {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Markdown => "md",
+            Format::Json => "json",
+        }
+    }
+}
+
+// the letters removed from one sentence and what was left of it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Step {
+    pub from: String,
+    pub letters: String,
+    pub remaining: String,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct Session {
+    pub sentence: String,
+    pub steps: Vec<Step>,
+    pub anagrams: Vec<String>,
+}
+
+impl Session {
+    pub fn new(sentence: &str) -> Session {
+        Session {
+            sentence: sentence.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn push_step(&mut self, from: &str, letters: &str, remaining: &str) {
+        self.steps.push(Step {
+            from: from.to_string(),
+            letters: letters.to_string(),
+            remaining: remaining.to_string(),
+        });
+    }
+
+    // ignores anagrams already found
+    pub fn add_anagram(&mut self, anagram: &str) {
+        if !self.anagrams.iter().any(|a| a == anagram) {
+            self.anagrams.push(anagram.to_string());
+        }
+    }
+
+    // what is left after the latest step, or the whole sentence before any
+    pub fn remaining(&self) -> &str {
+        match self.steps.last() {
+            Some(step) => &step.remaining,
+            None => &self.sentence,
+        }
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut md = String::new();
+        writeln!(md, "# ana session\n").unwrap();
+        writeln!(md, "**sentence:** {}\n", self.sentence).unwrap();
+
+        writeln!(md, "## steps\n").unwrap();
+        if self.steps.is_empty() {
+            writeln!(md, "_none_").unwrap();
+        }
+        for (i, step) in self.steps.iter().enumerate() {
+            writeln!(md, "{}. `{}` - `{}` -> `{}`", i + 1, step.from, step.letters, step.remaining).unwrap();
+        }
+
+        writeln!(md, "\n## remaining\n\n{}\n", self.remaining()).unwrap();
+
+        writeln!(md, "## anagrams\n").unwrap();
+        if self.anagrams.is_empty() {
+            writeln!(md, "_none_").unwrap();
+        }
+        for anagram in &self.anagrams {
+            writeln!(md, "- {}", anagram).unwrap();
+        }
+        md
+    }
+
+    pub fn to_json(&self) -> String {
+        #[derive(Serialize)]
+        struct Transcript<'a> {
+            #[serde(flatten)]
+            session: &'a Session,
+            remaining: &'a str,
+        }
+
+        let transcript = Transcript {
+            session: self,
+            remaining: self.remaining(),
+        };
+        serde_json::to_string_pretty(&transcript).expect("session is always serializable")
+    }
+
+    pub fn render(&self, format: Format) -> String {
+        match format {
+            Format::Markdown => self.to_markdown(),
+            Format::Json => self.to_json(),
+        }
+    }
+
+    pub fn export<P: AsRef<Path>>(&self, path: P, format: Format) -> io::Result<()> {
+        fs::write(path, self.render(format))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> Session {
+        let mut session = Session::new("haman is cool");
+        session.push_step("haman is cool", "hic", "aman s ool");
+        session.push_step("aman s ool", "man", "a s ool");
+        session.add_anagram("loco");
+        session.add_anagram("loco");
+        session
+    }
+
+    #[test]
+    fn remaining_works() {
+        assert_eq!(Session::new("haman").remaining(), "haman");
+        assert_eq!(session().remaining(), "a s ool");
+        assert_eq!(session().anagrams, vec!["loco"]);
+    }
+
+    #[test]
+    fn to_markdown_works() {
+        let md = session().to_markdown();
+        assert_eq!(md, "# ana session\n\n\
+            **sentence:** haman is cool\n\n\
+            ## steps\n\n\
+            1. `haman is cool` - `hic` -> `aman s ool`\n\
+            2. `aman s ool` - `man` -> `a s ool`\n\n\
+            ## remaining\n\n\
+            a s ool\n\n\
+            ## anagrams\n\n\
+            - loco\n");
+    }
+
+    #[test]
+    fn to_json_works() {
+        let json: serde_json::Value = serde_json::from_str(&session().to_json()).unwrap();
+        assert_eq!(json["sentence"], "haman is cool");
+        assert_eq!(json["steps"][1]["letters"], "man");
+        assert_eq!(json["remaining"], "a s ool");
+        assert_eq!(json["anagrams"][0], "loco");
+    }
+}
@@ -4,7 +4,6 @@ use std::io;
 use std::io::Write;
 use colored::*;
 use std::convert::TryInto;
-use libaq;
 const PROJECT_NAME: &str = "aq";
 const VERSION: &str = "0.1.0";
 const ABOUT: &str = "deCrypter for Anglobal communications";
@@ -29,7 +28,7 @@ fn main() {
 
     let query: String = match args.value_of("QUERY") {
         None => String::new(),
-        Some(query) => sanitize_query(&query),
+        Some(query) => sanitize_query(query),
     };
 
     if args.is_present("i") {
@@ -69,19 +68,18 @@ fn start_prompt(initial: &str) {
 
 // removes non-alphanumerics and converts to uppercase
 fn sanitize_query(q: &str) -> String {
-    return q
-        .chars()
+    q.chars()
         .filter(|&c| c.is_alphanumeric() || c.is_whitespace())
         .collect::<String>()
-        .to_uppercase();
+        .to_uppercase()
 }
 
 fn print_results(buffer: &String) {
     print!("{}", buffer);
-    for res in &libaq::nummificate(&sanitize_query(&buffer)) {
+    for res in &libaq::nummificate(&sanitize_query(buffer)) {
         print!(" -> {}", res);
     }
-    print!("\n");
+    println!();
 
     println!("{:->width$} THE IRON LAW OF SIX {:->width$}", "", "", width=40);
     let mut i = 0;
@@ -113,13 +111,9 @@ fn print_hex_trinomes(trinomes: &Vec<u8>) {
     for _ in 0..6 {
         print!("{} ", s.on_truecolor(trinomes[0]*SCALE, trinomes[1]*SCALE, trinomes[2]*SCALE));
     }
-    print!{"\n"};
+    println!();
 }
 
 fn is_quit(q: &str) -> bool {
-    match q {
-        "q" => true,
-        "Q" => true,
-        _ => false,
-    }
+    matches!(q, "q" | "Q")
 }
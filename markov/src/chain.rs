@@ -0,0 +1,831 @@
+// n-gram transition table
+use crate::rng::Rng;
+use crate::tokens;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::{self, BufRead};
+
+// a corpus the chain was trained on, and how much of the output should sound like it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Source {
+    pub name: String,
+    pub weight: f64,
+    pub ngrams: u64,
+}
+
+// a word seen after a prefix in one source, and how often
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Successor {
+    pub id: u32,
+    pub source: u16,
+    pub count: u32,
+}
+
+// every distinct (n-1)-word prefix, with the words that followed it and how often
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct State {
+    pub prefix: Vec<u32>,
+    pub next: Vec<Successor>,
+    pub total: u32,
+}
+
+// a run of generated text that all came from the same source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub source: usize,
+    pub text: String,
+}
+
+// how much text to compose
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Length {
+    Words(usize),
+    Sentences(usize),
+    Paragraphs(usize),
+}
+
+// a token that ends generation as soon as it is generated
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EndOn {
+    SentenceEnd,
+    Word(String),
+}
+
+impl EndOn {
+    fn matches(&self, token: &str) -> bool {
+        match self {
+            EndOn::SentenceEnd => tokens::is_sentence_end(token),
+            EndOn::Word(word) => token.to_lowercase() == word.to_lowercase(),
+        }
+    }
+}
+
+// "sentence" for the end of any sentence, anything else is a word to stop at
+impl std::str::FromStr for EndOn {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "sentence" => EndOn::SentenceEnd,
+            word => EndOn::Word(word.to_string()),
+        })
+    }
+}
+
+// how each next word is picked out of the words that followed the current prefix
+// the default picks in proportion to how often each did, and starts over from a random
+// state at a dead end
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sampling {
+    // below 1 favours the likeliest words, above 1 flattens the odds, 0 always picks the likeliest
+    pub temperature: f64,
+    // only the k likeliest words
+    pub top_k: Option<usize>,
+    // only the likeliest words that add up to this much of the probability
+    pub top_p: Option<f64>,
+    // at a dead end, carry on from the longest shorter prefix that has successors (stupid backoff)
+    pub backoff: bool,
+}
+
+impl Default for Sampling {
+    fn default() -> Self {
+        Sampling {
+            temperature: 1.0,
+            top_k: None,
+            top_p: None,
+            backoff: false,
+        }
+    }
+}
+
+// every run of n tokens, split into its first n-1 and the one that followed them
+// none at all when there are fewer than n tokens, or n is 0
+// EX: ngrams(&[a, b, c], 2) -> ([a], b), ([b], c)
+pub fn ngrams<T>(tokens: &[T], n: usize) -> impl Iterator<Item = (&[T], &T)> {
+    // windows never run past the end, unlike the old (itr, itr+n) pairs, but panic on 0
+    let windows = match n {
+        0 => [].windows(1),
+        n => tokens.windows(n),
+    };
+    windows.map(|ngram| {
+        let (last, prefix) = ngram.split_last().unwrap();
+        (prefix, last)
+    })
+}
+
+// whether a chain strings together the words of sentences, or the letters of words
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Level {
+    Word,
+    Char,
+}
+
+// keeps a corpus without sentence ends from walking forever
+const MAX_SENTENCE_TOKENS: usize = 200;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Chain {
+    order: usize,
+    level: Level,
+    sources: Vec<Source>,
+    // interned words, ids index into vocab
+    vocab: Vec<String>,
+    states: Vec<State>,
+    // the same for every shorter prefix, down to the empty one, for backing off
+    lower: Vec<State>,
+    // every word a character-level chain was trained on, so coined words can be new ones
+    lexicon: HashSet<String>,
+    // lookups, rebuilt from vocab and states after loading
+    #[serde(skip)]
+    ids: HashMap<String, u32>,
+    #[serde(skip)]
+    index: HashMap<Vec<u32>, usize>,
+    #[serde(skip)]
+    lower_index: HashMap<Vec<u32>, usize>,
+    // set at run time, models don't keep it
+    #[serde(skip)]
+    sampling: Sampling,
+}
+
+impl Chain {
+    // order is the n in n-gram, the size of each sliding window
+    pub fn new(order: usize) -> Chain {
+        Chain::with_level(order, Level::Word)
+    }
+
+    // a chain over the letters of words, for coining new ones
+    pub fn chars(order: usize) -> Chain {
+        Chain::with_level(order, Level::Char)
+    }
+
+    pub fn with_level(order: usize, level: Level) -> Chain {
+        assert!(order > 0, "n-gram order must be at least 1");
+        Chain {
+            order,
+            level,
+            sources: Vec::new(),
+            vocab: Vec::new(),
+            states: Vec::new(),
+            lower: Vec::new(),
+            lexicon: HashSet::new(),
+            ids: HashMap::new(),
+            index: HashMap::new(),
+            lower_index: HashMap::new(),
+            sampling: Sampling::default(),
+        }
+    }
+
+    // rebuilds the lookups that aren't saved with a model
+    pub(crate) fn reindex(&mut self) {
+        self.ids = self.vocab.iter().enumerate().map(|(id, word)| (word.clone(), id as u32)).collect();
+        self.index = self.states.iter().enumerate().map(|(i, state)| (state.prefix.clone(), i)).collect();
+        self.lower_index = self.lower.iter().enumerate().map(|(i, state)| (state.prefix.clone(), i)).collect();
+    }
+
+    pub fn sampling(&self) -> Sampling {
+        self.sampling
+    }
+
+    pub fn set_sampling(&mut self, sampling: Sampling) {
+        self.sampling = sampling;
+    }
+
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    pub fn level(&self) -> Level {
+        self.level
+    }
+
+    pub fn vocab(&self) -> &[String] {
+        &self.vocab
+    }
+
+    pub fn states(&self) -> &[State] {
+        &self.states
+    }
+
+    pub fn sources(&self) -> &[Source] {
+        &self.sources
+    }
+
+    pub fn word(&self, id: u32) -> &str {
+        &self.vocab[id as usize]
+    }
+
+    pub fn id(&self, word: &str) -> Option<u32> {
+        self.ids.get(word).copied()
+    }
+
+    fn intern(&mut self, word: &str) -> u32 {
+        if let Some(&id) = self.ids.get(word) {
+            return id;
+        }
+        let id = self.vocab.len() as u32;
+        self.vocab.push(word.to_string());
+        self.ids.insert(word.to_string(), id);
+        id
+    }
+
+    // weights are relative to each other and to the size of each source,
+    // so 0.7 and 0.3 give 70% and 30% of the output whatever the corpus lengths
+    pub fn add_source(&mut self, name: &str, weight: f64) -> usize {
+        assert!(self.sources.len() < u16::MAX as usize, "too many sources");
+        assert!(weight.is_finite() && weight >= 0.0, "source weights must be finite and not negative");
+        self.sources.push(Source {
+            name: name.to_string(),
+            weight,
+            ngrams: 0,
+        });
+        self.sources.len() - 1
+    }
+
+    // counts every n-gram of words into the table, as the first source
+    pub fn train<S: AsRef<str>>(&mut self, words: &[S]) {
+        if self.sources.is_empty() {
+            self.add_source("corpus", 1.0);
+        }
+        self.train_source(0, words);
+    }
+
+    // counts every n-gram of words into the table, as source
+    pub fn train_source<S: AsRef<str>>(&mut self, source: usize, words: &[S]) {
+        let ids: Vec<u32> = words.iter().map(|word| self.intern(word.as_ref())).collect();
+        self.train_ids(source, &[], &ids);
+    }
+
+    // counts a corpus into the table a line at a time, as source, without reading it whole
+    // the same as training on tokenize (or words, if plain) of all of it
+    pub fn train_reader<R: BufRead>(&mut self, source: usize, reader: R, plain: bool) -> io::Result<()> {
+        let plain = plain || self.level == Level::Char;
+        // n-grams run on from one line to the next
+        let mut before: Vec<u32> = Vec::new();
+        for line in tokens::read(reader, plain) {
+            let line = line?;
+            if self.level == Level::Char {
+                self.train_chars(source, &line);
+                continue;
+            }
+            let ids: Vec<u32> = line.iter().map(|word| self.intern(word)).collect();
+            self.train_ids(source, &before, &ids);
+            before.extend(ids);
+            let keep = before.len().saturating_sub(self.order - 1);
+            before.drain(..keep);
+        }
+        Ok(())
+    }
+
+    // counts every n-gram that ends in ids, carrying on from the tokens before them
+    fn train_ids(&mut self, source: usize, before: &[u32], ids: &[u32]) {
+        let tokens = [before, ids].concat();
+        let from = |n: usize| before.len().saturating_sub(n - 1);
+        // every shorter n-gram too, down to single words, so there is always something to back off to
+        for n in 1..=self.order {
+            for (prefix, &next) in ngrams(&tokens[from(n)..], n) {
+                self.observe(prefix, next, source as u16);
+            }
+        }
+        self.sources[source].ngrams += ngrams(&tokens[from(self.order)..], self.order).count() as u64;
+    }
+
+    // counts the letters of every word into the table, as source
+    // each word is padded with BOUNDARY so the chain learns how words start and end
+    pub fn train_chars<S: AsRef<str>>(&mut self, source: usize, words: &[S]) {
+        for word in words {
+            let letters = tokens::chars(word.as_ref());
+            if letters.is_empty() {
+                continue;
+            }
+            self.lexicon.insert(letters.concat());
+            let mut padded = vec![tokens::BOUNDARY.to_string(); self.order - 1];
+            padded.extend(letters);
+            padded.push(tokens::BOUNDARY.to_string());
+            self.train_source(source, &padded);
+        }
+    }
+
+    // whether a character-level chain was trained on word
+    pub fn knows(&self, word: &str) -> bool {
+        self.lexicon.contains(&tokens::chars(word).concat())
+    }
+
+    fn observe(&mut self, prefix: &[u32], next: u32, source: u16) {
+        let (index, states) = match prefix.len() == self.order - 1 {
+            true => (&mut self.index, &mut self.states),
+            false => (&mut self.lower_index, &mut self.lower),
+        };
+        let i = match index.get(prefix) {
+            Some(&i) => i,
+            None => {
+                index.insert(prefix.to_vec(), states.len());
+                states.push(State {
+                    prefix: prefix.to_vec(),
+                    ..Default::default()
+                });
+                states.len() - 1
+            }
+        };
+
+        let state = &mut states[i];
+        state.total += 1;
+        // full prefixes keep successors in the order they were seen, which seeded output depends on
+        // shorter ones can be followed by most of the vocabulary, so they are kept sorted to search
+        if prefix.len() == self.order - 1 {
+            match state.next.iter_mut().find(|s| s.id == next && s.source == source) {
+                Some(successor) => successor.count += 1,
+                None => state.next.push(Successor { id: next, source, count: 1 }),
+            }
+        } else {
+            match state.next.binary_search_by_key(&(next, source), |s| (s.id, s.source)) {
+                Ok(i) => state.next[i].count += 1,
+                Err(i) => state.next.insert(i, Successor { id: next, source, count: 1 }),
+            }
+        }
+    }
+
+    // the state of a prefix of any length up to n-1
+    pub fn state(&self, prefix: &[u32]) -> Option<&State> {
+        match prefix.len() == self.order - 1 {
+            true => self.index.get(prefix).map(|&i| &self.states[i]),
+            false => self.lower_index.get(prefix).map(|&i| &self.lower[i]),
+        }
+    }
+
+    // a single corpus is sampled with exact integer counts, blends need the weights
+    fn blended(&self) -> bool {
+        self.sources.len() > 1
+    }
+
+    // a source's weight over the sum of them all, so huge weights can't add up to infinity
+    fn share(&self, source: &Source) -> f64 {
+        let max = self.sources.iter().map(|s| s.weight).fold(0.0, f64::max);
+        if max <= 0.0 {
+            return 0.0;
+        }
+        let total: f64 = self.sources.iter().map(|s| s.weight / max).sum();
+        source.weight / max / total
+    }
+
+    fn weight(&self, successor: &Successor) -> f64 {
+        let source = &self.sources[successor.source as usize];
+        match source.ngrams {
+            0 => 0.0,
+            n => successor.count as f64 * self.share(source) / n as f64,
+        }
+    }
+
+    fn state_weight(&self, state: &State) -> f64 {
+        state.next.iter().map(|s| self.weight(s)).sum()
+    }
+
+    // how likely successor is to follow state, out of all of its successors
+    pub(crate) fn probability(&self, state: &State, successor: &Successor) -> f64 {
+        if self.blended() {
+            match self.state_weight(state) {
+                total if total > 0.0 => self.weight(successor) / total,
+                _ => 0.0,
+            }
+        } else {
+            successor.count as f64 / state.total as f64
+        }
+    }
+
+    // a state that keep accepts, picked in proportion to how often its prefix was seen
+    pub(crate) fn random_state<F>(&self, rng: &mut Rng, keep: F) -> Option<&State>
+    where
+        F: Fn(&State) -> bool,
+    {
+        if self.blended() {
+            let weight = |state: &State| if keep(state) { self.state_weight(state) } else { 0.0 };
+            let total: f64 = self.states.iter().map(weight).sum();
+            if total <= 0.0 {
+                return None;
+            }
+            let mut r = rng.next_f64() * total;
+            for state in &self.states {
+                if r < weight(state) {
+                    return Some(state);
+                }
+                r -= weight(state);
+            }
+            return self.states.iter().rev().find(|&state| weight(state) > 0.0);
+        }
+
+        let count = |state: &State| if keep(state) { state.total as u64 } else { 0 };
+        let total: u64 = self.states.iter().map(count).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut r = rng.next_u64() % total;
+        for state in &self.states {
+            if r < count(state) {
+                return Some(state);
+            }
+            r -= count(state);
+        }
+        None
+    }
+
+    // whether the next word after state starts a sentence
+    fn starts_sentence(&self, state: &State) -> bool {
+        match state.prefix.last() {
+            Some(&id) => tokens::is_sentence_end(self.word(id)) || self.word(id) == tokens::PARAGRAPH,
+            None => true,
+        }
+    }
+
+    // a word that followed state, picked as sampling says
+    // None if every source that continues state has no weight
+    fn sample(&self, state: &State, rng: &mut Rng) -> Option<Successor> {
+        let Sampling { temperature, top_k, top_p, .. } = self.sampling;
+        if temperature == 1.0 && top_k.is_none() && top_p.is_none() {
+            return self.sample_counts(state, rng).copied();
+        }
+
+        // the same word from several sources is one choice, credited to the source it came from most
+        let mut choices: Vec<(Successor, f64, f64)> = Vec::with_capacity(state.next.len());
+        for successor in &state.next {
+            let p = self.probability(state, successor);
+            let merged = match self.blended() {
+                true => choices.iter_mut().find(|(s, _, _)| s.id == successor.id),
+                false => None,
+            };
+            match merged {
+                Some((best, total, most)) => {
+                    *total += p;
+                    if p > *most {
+                        *best = *successor;
+                        *most = p;
+                    }
+                }
+                None => choices.push((*successor, p, p)),
+            }
+        }
+        let mut choices: Vec<(Successor, f64)> = choices
+            .into_iter()
+            .filter(|&(_, p, _)| p > 0.0)
+            .map(|(successor, p, _)| (successor, p))
+            .collect();
+        choices.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        if choices.is_empty() {
+            return None;
+        }
+
+        if temperature <= 0.0 {
+            return Some(choices[0].0);
+        }
+        // relative to the likeliest, so at low temperatures the odds don't all underflow to 0
+        let max = choices[0].1;
+        for choice in choices.iter_mut() {
+            choice.1 = (choice.1 / max).powf(1.0 / temperature);
+        }
+        if let Some(k) = top_k {
+            choices.truncate(k.max(1));
+        }
+        if let Some(top_p) = top_p {
+            let total: f64 = choices.iter().map(|c| c.1).sum();
+            let mut kept = 0.0;
+            let keep = choices.iter().take_while(|c| {
+                let under = kept < top_p * total;
+                kept += c.1;
+                under
+            });
+            let keep = keep.count().max(1);
+            choices.truncate(keep);
+        }
+
+        let total: f64 = choices.iter().map(|c| c.1).sum();
+        let mut r = rng.next_f64() * total;
+        for &(successor, weight) in &choices {
+            if r < weight {
+                return Some(successor);
+            }
+            r -= weight;
+        }
+        choices.last().map(|c| c.0)
+    }
+
+    // a word that followed state, picked in proportion to how often it did
+    fn sample_counts<'a>(&self, state: &'a State, rng: &mut Rng) -> Option<&'a Successor> {
+        if self.blended() {
+            let total = self.state_weight(state);
+            if total <= 0.0 {
+                return None;
+            }
+            let mut r = rng.next_f64() * total;
+            for successor in &state.next {
+                let weight = self.weight(successor);
+                if r < weight {
+                    return Some(successor);
+                }
+                r -= weight;
+            }
+            return state.next.iter().rev().find(|s| self.weight(s) > 0.0);
+        }
+
+        let mut r = rng.below(state.total as usize) as u32;
+        for successor in &state.next {
+            if r < successor.count {
+                return Some(successor);
+            }
+            r -= successor.count;
+        }
+        unreachable!("counts always add up to total")
+    }
+
+    // the next word after context, from its own state or, backing off, the longest end of it that has one
+    // None at a dead end
+    fn next(&self, context: &[u32], rng: &mut Rng) -> Option<Successor> {
+        let known = self.state(context).filter(|_| context.len() == self.order - 1);
+        if let Some(next) = known.and_then(|state| self.sample(state, rng)) {
+            return Some(next);
+        }
+        if !self.sampling.backoff {
+            return None;
+        }
+        let longest = context.len().min(self.order - 1);
+        (0..=longest)
+            .rev()
+            .filter(|&k| k < self.order - 1)
+            .filter_map(|k| self.state(&context[context.len() - k..]))
+            .find_map(|state| self.sample(state, rng))
+    }
+
+    // the ids of the last n-1 words of phrase, up to the last one the chain doesn't know
+    // a character-level chain spells a word at a time, so only the letters since the last boundary count,
+    // after the boundaries every word was padded with in training
+    pub(crate) fn known_context<S: AsRef<str>>(&self, phrase: &[S]) -> Vec<u32> {
+        let mut phrase: Vec<&str> = phrase.iter().map(|word| word.as_ref()).collect();
+        if self.level == Level::Char {
+            let start = phrase.iter().rposition(|&letter| letter == tokens::BOUNDARY).map_or(0, |i| i + 1);
+            phrase.splice(..start, vec![tokens::BOUNDARY; self.order - 1]);
+        }
+        let context: Vec<u32> = phrase.iter().rev().take(self.order - 1).map_while(|word| self.id(word)).collect();
+        context.into_iter().rev().collect()
+    }
+
+    // the next word after the words so far, picked as sampling says
+    // where the chain can't carry on from them, from the nearest state it can, or from a random one
+    pub fn pick<S: AsRef<str>>(&self, words: &[S], rng: &mut Rng) -> Option<&str> {
+        let next = match self.next(&self.known_context(words), rng) {
+            Some(next) => next,
+            None => {
+                let state = match words.is_empty() {
+                    true => self.random_state(rng, |state| self.starts_sentence(state)),
+                    false => self.nearest_state(words, rng),
+                };
+                let state = state.or_else(|| self.random_state(rng, |_| true))?;
+                self.sample(state, rng)?
+            }
+        };
+        Some(self.word(next.id))
+    }
+
+    // the state closest to the end of a phrase: the state of its last n-1 words if there is one,
+    // otherwise a random state whose prefix ends with as many of them as possible
+    // None if the chain doesn't know the phrase's last word
+    fn nearest_state<S: AsRef<str>>(&self, phrase: &[S], rng: &mut Rng) -> Option<&State> {
+        let ids = self.known_context(phrase);
+        for k in (1..=ids.len()).rev() {
+            let suffix = &ids[ids.len() - k..];
+            let found = match k == self.order - 1 {
+                true => self.state(suffix),
+                false => self.random_state(rng, |state| state.prefix.ends_with(suffix)),
+            };
+            if found.is_some() {
+                return found;
+            }
+        }
+        None
+    }
+
+    // walks the chain from the state nearest the end of start, or from a random one, a word at a time
+    // each word is picked given the n-1 before it, see Walk
+    // with sentences, every start is at the beginning of a sentence and its prefix is left out
+    // length only counts the words after start, which aren't repeated
+    pub fn walk<'a, S: AsRef<str>>(
+        &'a self,
+        start: &[S],
+        sentences: bool,
+        length: Length,
+        end_on: Option<&EndOn>,
+        rng: &'a mut Rng,
+    ) -> Walk<'a> {
+        let current = self.nearest_state(start, rng).map(|state| state.prefix.clone()).unwrap_or_default();
+        Walk {
+            chain: self,
+            rng,
+            sentences,
+            length,
+            end_on: end_on.cloned(),
+            current,
+            pending: VecDeque::new(),
+            blank: start.is_empty(),
+            count: 0,
+            ends: 0,
+            stopped: false,
+            done: matches!(length, Length::Words(0) | Length::Sentences(0) | Length::Paragraphs(0)),
+        }
+    }
+
+    pub fn generate(&self, words: usize, rng: &mut Rng) -> String {
+        self.generate_from::<&str>(&[], words, None, rng)
+    }
+
+    // words more words after start, carrying on from the state nearest to its end
+    // a start the chain knows nothing about is generated from a random state
+    pub fn generate_from<S: AsRef<str>>(&self, start: &[S], words: usize, end_on: Option<&EndOn>, rng: &mut Rng) -> String {
+        let continued = self.walk(start, false, Length::Words(words), end_on, rng).map(|(word, _)| word);
+        start.iter().map(|word| word.as_ref()).chain(continued).collect::<Vec<_>>().join(" ")
+    }
+
+    // starts at the beginning of a sentence and stops once there is enough text,
+    // then lays the tokens out as prose
+    pub fn compose(&self, length: Length, rng: &mut Rng) -> String {
+        self.compose_from::<&str>(&[], length, None, rng)
+    }
+
+    // same as compose, but carrying on from start instead of a new sentence
+    // length only counts the text after start
+    pub fn compose_from<S: AsRef<str>>(&self, start: &[S], length: Length, end_on: Option<&EndOn>, rng: &mut Rng) -> String {
+        let continued = self.walk(start, true, length, end_on, rng).map(|(word, _)| word);
+        let words: Vec<&str> = start.iter().map(|word| word.as_ref()).chain(continued).collect();
+        tokens::detokenize(&words)
+    }
+
+    // same as generate but split into spans, each traced back to its source
+    pub fn generate_traced(&self, words: usize, rng: &mut Rng) -> Vec<Span> {
+        let mut spans: Vec<Span> = Vec::new();
+        for (word, source) in self.walk::<&str>(&[], false, Length::Words(words), None, rng) {
+            match spans.last_mut() {
+                Some(span) if span.source == source => {
+                    span.text.push(' ');
+                    span.text.push_str(word);
+                }
+                _ => spans.push(Span {
+                    source,
+                    text: word.to_string(),
+                }),
+            }
+        }
+        spans
+    }
+
+    // a single word, spelled from the start of a word until the chain reaches its end
+    // None if it runs past max_len letters or ends before it starts
+    pub fn coin(&self, max_len: usize, rng: &mut Rng) -> Option<String> {
+        let boundary = self.id(tokens::BOUNDARY)?;
+        let mut current = vec![boundary; self.order - 1];
+        let mut word = String::new();
+        for _ in 0..=max_len {
+            let next = self.next(&current, rng)?.id;
+            if next == boundary {
+                return Some(word).filter(|word| !word.is_empty());
+            }
+            word.push_str(self.word(next));
+            current.push(next);
+            if current.len() >= self.order {
+                current.remove(0);
+            }
+        }
+        None
+    }
+
+    // up to count distinct words between min_len and max_len letters that the chain wasn't trained on
+    // gives up after a number of failed attempts, so a small lexicon can return fewer
+    pub fn coin_words(&self, count: usize, min_len: usize, max_len: usize, rng: &mut Rng) -> Vec<String> {
+        let mut coined: Vec<String> = Vec::new();
+        let mut attempts = count * 100;
+        while coined.len() < count && attempts > 0 {
+            attempts -= 1;
+            let word = match self.coin(max_len, rng) {
+                Some(word) => word,
+                None => continue,
+            };
+            if word.chars().count() >= min_len && !self.knows(&word) && !coined.contains(&word) {
+                coined.push(word);
+            }
+        }
+        coined
+    }
+}
+
+// a walk through a chain, handing out each word with the source it came from as it is picked
+// starts over from a new random state when it reaches a prefix with no successors
+// stops once there is enough text, right after a word that end_on matches, or if the chain is empty
+pub struct Walk<'a> {
+    chain: &'a Chain,
+    rng: &'a mut Rng,
+    sentences: bool,
+    length: Length,
+    end_on: Option<EndOn>,
+    // the last n-1 words
+    current: Vec<u32>,
+    // the prefix of a new start, waiting to be handed out before the word that followed it
+    pending: VecDeque<(u32, u16)>,
+    // nothing has been handed out yet, here or by the caller, so a paragraph break would open with a blank line
+    blank: bool,
+    count: usize,
+    // sentence or paragraph ends so far
+    ends: usize,
+    // no more words will be picked
+    stopped: bool,
+    // no more words will be handed out
+    done: bool,
+}
+
+impl Walk<'_> {
+    fn step(&mut self) -> Option<(u32, u16)> {
+        if let Some(next) = self.pending.pop_front() {
+            return Some(next);
+        }
+        if self.stopped {
+            return None;
+        }
+
+        let chain = self.chain;
+        // a walk opens from a random state, rather than backing off from nothing
+        let opened = !self.current.is_empty() || chain.order == 1;
+        let next = match opened.then(|| chain.next(&self.current, self.rng)).flatten() {
+            Some(next) => next,
+            None => {
+                let start = match self.sentences {
+                    true => chain
+                        .random_state(self.rng, |state| chain.starts_sentence(state))
+                        .or_else(|| chain.random_state(self.rng, |_| true)),
+                    false => chain.random_state(self.rng, |_| true),
+                };
+                let state = match start {
+                    Some(state) => state,
+                    None => {
+                        self.stopped = true;
+                        return None;
+                    }
+                };
+                let next = chain.sample(state, self.rng).expect("random states have weight");
+                if !self.sentences {
+                    self.pending.extend(state.prefix.iter().map(|&id| (id, next.source)));
+                }
+                self.current = state.prefix.clone();
+                next
+            }
+        };
+        self.pending.push_back((next.id, next.source));
+        self.current.push(next.id);
+        if self.current.len() >= chain.order {
+            self.current.remove(0);
+        }
+        if self.end_on.as_ref().is_some_and(|end_on| end_on.matches(chain.word(next.id))) {
+            self.stopped = true;
+        }
+        self.pending.pop_front()
+    }
+}
+
+impl<'a> Iterator for Walk<'a> {
+    type Item = (&'a str, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        // a walk that opens on a sentence end is often followed by a paragraph break,
+        // which is no text at all and doesn't count towards the length
+        let mut skipped = 0;
+        let (id, source) = loop {
+            let (id, source) = self.step()?;
+            if !self.blank || self.chain.word(id) != tokens::PARAGRAPH {
+                break (id, source);
+            }
+            skipped += 1;
+            if skipped >= MAX_SENTENCE_TOKENS {
+                self.done = true;
+                return None;
+            }
+        };
+        self.blank = false;
+        let word = self.chain.word(id);
+        self.count += 1;
+        match self.length {
+            Length::Words(words) => self.done = self.count >= words,
+            Length::Sentences(sentences) => {
+                if tokens::is_sentence_end(word) {
+                    self.ends += 1;
+                }
+                self.done = self.ends >= sentences || self.count >= sentences * MAX_SENTENCE_TOKENS;
+            }
+            Length::Paragraphs(paragraphs) => {
+                if word == tokens::PARAGRAPH {
+                    self.ends += 1;
+                }
+                self.done = self.ends >= paragraphs || self.count >= paragraphs * MAX_SENTENCE_TOKENS * 10;
+                // the last paragraph needs no break after it
+                if self.done && word == tokens::PARAGRAPH {
+                    return None;
+                }
+            }
+        }
+        Some((word, source as usize))
+    }
+}
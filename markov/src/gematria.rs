@@ -0,0 +1,164 @@
+// phrases that add up to a chosen AQ value, found with a beam search over the chain
+// values come from the aq crate, so they match what `aq` prints for the same phrase
+use crate::chain::Chain;
+use crate::rng::Rng;
+use crate::tokens;
+use std::collections::HashSet;
+
+// what a phrase has to add up to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    // exactly this AQ value
+    Value(i32),
+    // any value whose reduction chain ends in this digit
+    Reduces(i32),
+}
+
+impl Target {
+    fn reached(&self, text: &str) -> bool {
+        match *self {
+            Target::Value(target) => value(text) == target,
+            Target::Reduces(digit) => reduce(text) == digit,
+        }
+    }
+
+    // values only grow as words are added, so a phrase past the target is a dead end
+    fn exceeded(&self, value: i32) -> bool {
+        match *self {
+            Target::Value(target) => value > target,
+            Target::Reduces(_) => false,
+        }
+    }
+}
+
+// the AQ value of text, in any case
+// EX: value("aok") -> 54
+pub fn value(text: &str) -> i32 {
+    libaq::gematria(&text.to_uppercase(), libaq::AQ)
+}
+
+// the last number in the reduction chain of text
+// EX: reduce("aok") -> 9
+pub fn reduce(text: &str) -> i32 {
+    *libaq::nummificate(&text.to_uppercase()).last().unwrap()
+}
+
+// how wide and how deep to search
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Search {
+    // phrases kept after each word, the likeliest first
+    pub beam: usize,
+    pub min_words: usize,
+    pub max_words: usize,
+}
+
+impl Default for Search {
+    fn default() -> Self {
+        Search {
+            beam: 256,
+            min_words: 2,
+            max_words: 12,
+        }
+    }
+}
+
+// a phrase on the beam, with its value so far and the log probability of its words
+#[derive(Debug, Clone)]
+struct Candidate {
+    ids: Vec<u32>,
+    value: i32,
+    score: f64,
+}
+
+impl Chain {
+    // up to count distinct phrases that hit target, the likeliest (per word) first
+    // the search starts from beam random states, so different seeds find different phrases
+    pub fn search(&self, target: Target, search: &Search, count: usize, rng: &mut Rng) -> Vec<String> {
+        let values: Vec<i32> = self.vocab().iter().map(|word| value(word)).collect();
+        let is_word = |id: u32| self.word(id).chars().any(char::is_alphanumeric);
+
+        let mut beam: Vec<Candidate> = Vec::new();
+        for _ in 0..search.beam {
+            let state = match self.random_state(rng, |_| true) {
+                Some(state) => state,
+                None => break,
+            };
+            if beam.iter().all(|c| c.ids != state.prefix) {
+                beam.push(Candidate {
+                    ids: state.prefix.clone(),
+                    value: state.prefix.iter().map(|&id| values[id as usize]).sum(),
+                    score: 0.0,
+                });
+            }
+        }
+        // a phrase shouldn't open with punctuation
+        beam.retain(|c| c.ids.first().is_none_or(|&id| is_word(id)) && !target.exceeded(c.value));
+
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut found: Vec<(f64, String)> = Vec::new();
+        while !beam.is_empty() {
+            for candidate in &beam {
+                // or close with it, which would also find the same phrase twice
+                if candidate.ids.len() < search.min_words || !candidate.ids.last().is_some_and(|&id| is_word(id)) {
+                    continue;
+                }
+                let text = self.text(&candidate.ids);
+                if target.reached(&text) && seen.insert(text.clone()) {
+                    found.push((candidate.score / candidate.ids.len() as f64, text));
+                }
+            }
+            if beam[0].ids.len() >= search.max_words {
+                break;
+            }
+            beam = self.widen(&beam, &values, target, search.beam);
+        }
+
+        found.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+        found.into_iter().take(count).map(|(_, text)| text).collect()
+    }
+
+    // the likeliest width candidates one word longer, that can still reach target
+    fn widen(&self, beam: &[Candidate], values: &[i32], target: Target, width: usize) -> Vec<Candidate> {
+        // (candidate, next word, value, score), only the survivors are copied into candidates
+        let mut steps: Vec<(usize, u32, i32, f64)> = Vec::new();
+        for (i, candidate) in beam.iter().enumerate() {
+            let context = &candidate.ids[candidate.ids.len() + 1 - self.order()..];
+            let state = match self.state(context) {
+                Some(state) => state,
+                None => continue,
+            };
+            // the same word from several sources is one step
+            let mut next: Vec<(u32, f64)> = Vec::new();
+            for successor in &state.next {
+                let p = self.probability(state, successor);
+                match next.iter_mut().find(|(id, _)| *id == successor.id) {
+                    Some((_, total)) => *total += p,
+                    None => next.push((successor.id, p)),
+                }
+            }
+
+            for (id, p) in next {
+                let value = candidate.value + values[id as usize];
+                if p > 0.0 && !target.exceeded(value) {
+                    steps.push((i, id, value, candidate.score + p.ln()));
+                }
+            }
+        }
+
+        steps.sort_by(|a, b| b.3.partial_cmp(&a.3).unwrap());
+        steps
+            .into_iter()
+            .take(width)
+            .map(|(i, id, value, score)| {
+                let mut ids = beam[i].ids.clone();
+                ids.push(id);
+                Candidate { ids, value, score }
+            })
+            .collect()
+    }
+
+    fn text(&self, ids: &[u32]) -> String {
+        let words: Vec<&str> = ids.iter().map(|&id| self.word(id)).collect();
+        tokens::detokenize(&words)
+    }
+}
@@ -0,0 +1,628 @@
+//! markov - n-gram text generation, in plain rust
+pub mod chain;
+pub mod gematria;
+pub mod model;
+pub mod rng;
+pub mod stats;
+pub mod tokens;
+
+pub use chain::{Chain, EndOn, Length, Level, Sampling, Span, Walk};
+pub use rng::Rng;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn words_works() {
+        assert_eq!(tokens::words("the 3 labyrinth's\ninterior,  told"), vec!["the", "labyrinths", "interior", "told"]);
+        assert!(tokens::words("").is_empty());
+        assert!(tokens::words("42 - ...").is_empty());
+    }
+
+    #[test]
+    fn train_builds_transition_table() {
+        let mut chain = Chain::new(3);
+        chain.train(&tokens::words("the labyrinth is the labyrinth of the labyrinth"));
+        assert_eq!(chain.vocab(), ["the", "labyrinth", "is", "of"]);
+
+        let the_labyrinth = [chain.id("the").unwrap(), chain.id("labyrinth").unwrap()];
+        let state = chain.state(&the_labyrinth).unwrap();
+        assert_eq!(state.total, 2);
+        let next: Vec<(u32, u32)> = state.next.iter().map(|s| (s.id, s.count)).collect();
+        assert_eq!(next, vec![(chain.id("is").unwrap(), 1), (chain.id("of").unwrap(), 1)]);
+        assert_eq!(chain.states().len(), 5);
+    }
+
+    #[test]
+    fn generate_follows_transitions() {
+        let text = "only nausea and fear are there only fear and nausea are there";
+        let mut chain = Chain::new(3);
+        chain.train(&tokens::words(text));
+
+        let generated = chain.generate(50, &mut Rng::new(6));
+        let words: Vec<&str> = generated.split(' ').collect();
+        assert_eq!(words.len(), 50);
+        // every step is a transition seen in training, or a restart at a known prefix
+        for w in words.windows(3) {
+            let prefix = [chain.id(w[0]).unwrap(), chain.id(w[1]).unwrap()];
+            let next = chain.id(w[2]).unwrap();
+            let continued = chain.state(&prefix).is_some_and(|s| s.next.iter().any(|s| s.id == next));
+            let restarted = chain.state(&[prefix[1], next]).is_some();
+            assert!(continued || restarted, "{:?}", w);
+        }
+    }
+
+    #[test]
+    fn generate_deterministic_corpus() {
+        // only one way through
+        let mut chain = Chain::new(2);
+        chain.train(&["a", "b", "c", "a"]);
+        let generated = chain.generate(6, &mut Rng::new(1));
+        assert!("a b c a b c a b c".contains(&generated));
+    }
+
+    #[test]
+    fn generate_short_corpus() {
+        let mut chain = Chain::new(4);
+        chain.train(&["too", "short"]);
+        assert_eq!(chain.generate(10, &mut Rng::new(0)), "");
+        assert_eq!(Chain::new(4).generate(10, &mut Rng::new(0)), "");
+    }
+
+    #[test]
+    fn ngrams_stay_in_bounds() {
+        let tokens = ["a", "b", "c"];
+        let pairs: Vec<(&[&str], &&str)> = chain::ngrams(&tokens, 2).collect();
+        assert_eq!(pairs, vec![(&["a"][..], &"b"), (&["b"][..], &"c")]);
+        assert_eq!(chain::ngrams(&tokens, 3).count(), 1);
+        assert_eq!(chain::ngrams(&tokens, 1).map(|(prefix, _)| prefix.len()).collect::<Vec<_>>(), vec![0, 0, 0]);
+        assert_eq!(chain::ngrams(&tokens, 4).count(), 0);
+        assert_eq!(chain::ngrams(&tokens, 0).count(), 0);
+        assert_eq!(chain::ngrams::<&str>(&[], 1).count(), 0);
+    }
+
+    #[test]
+    fn short_and_empty_corpora() {
+        for corpus in [&[][..], &["too"][..], &["too", "short"][..], &["still", "too", "short"][..]] {
+            let mut chain = Chain::new(4);
+            chain.train(corpus);
+            assert!(chain.states().is_empty());
+            assert_eq!(chain.sources()[0].ngrams, 0);
+            assert_eq!(chain.generate(10, &mut Rng::new(0)), "");
+            assert_eq!(chain.compose(Length::Sentences(2), &mut Rng::new(0)), "");
+            assert_eq!(chain.generate_from(&["too"], 10, None, &mut Rng::new(0)), "too");
+            let search = gematria::Search::default();
+            assert!(chain.search(gematria::Target::Reduces(1), &search, 5, &mut Rng::new(0)).is_empty());
+
+            chain.set_sampling(Sampling { backoff: true, ..Default::default() });
+            assert_eq!(chain.generate(10, &mut Rng::new(0)), "");
+        }
+
+        // exactly n words is a single n-gram
+        let mut chain = Chain::new(4);
+        chain.train(&["just", "long", "enough", "now"]);
+        assert_eq!(chain.states().len(), 1);
+        assert_eq!(chain.generate(4, &mut Rng::new(0)), "just long enough now");
+
+        let mut chain = Chain::chars(4);
+        chain.train_chars(0, &[""; 0]);
+        assert_eq!(chain.coin(10, &mut Rng::new(0)), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least 1")]
+    fn order_zero_is_refused() {
+        Chain::new(0);
+    }
+
+    #[test]
+    fn generate_unigrams() {
+        let mut chain = Chain::new(1);
+        chain.train(&["fear", "fear"]);
+        assert_eq!(chain.generate(3, &mut Rng::new(0)), "fear fear fear");
+    }
+
+    #[test]
+    fn rng_is_reproducible() {
+        let mut rng = Rng::new(42);
+        assert_eq!(rng.next_u64(), 13679457532755275413);
+        assert_eq!(rng.next_u64(), 2949826092126892291);
+        assert_eq!(rng.below(10), 8);
+    }
+
+    #[test]
+    fn generate_with_seed() {
+        let text = "only nausea and fear are there only fear and nausea are there and nausea is only fear";
+        let mut chain = Chain::new(2);
+        chain.train(&tokens::words(text));
+        assert_eq!(
+            chain.generate(12, &mut Rng::new(666)),
+            "only nausea are there only fear and nausea is only nausea is"
+        );
+        assert_eq!(
+            chain.generate(12, &mut Rng::new(93)),
+            "and fear are there and nausea are there only fear and nausea"
+        );
+        assert_eq!(chain.generate(12, &mut Rng::new(666)), chain.generate(12, &mut Rng::new(666)));
+    }
+
+    #[test]
+    fn model_round_trip() {
+        let mut chain = Chain::new(3);
+        chain.train(&tokens::words("only nausea and fear are there only fear and nausea are there"));
+
+        let mut bytes = Vec::new();
+        model::save(&chain, &mut bytes).unwrap();
+        assert_eq!(&bytes[..8], b"MRKV\x04\0\0\0");
+
+        let loaded = model::load(&bytes[..]).unwrap();
+        assert_eq!(loaded.vocab(), chain.vocab());
+        assert_eq!(loaded.id("fear"), chain.id("fear"));
+        assert_eq!(loaded.generate(20, &mut Rng::new(3)), chain.generate(20, &mut Rng::new(3)));
+    }
+
+    #[test]
+    fn model_rejects_other_files() {
+        assert!(model::load(&b"only nausea and fear"[..]).is_err());
+        assert!(model::load(&b"MRKV\x63\0\0\0"[..]).is_err());
+        assert!(model::load(&b"MRKV"[..]).is_err());
+    }
+
+    #[test]
+    fn blended_sources_follow_weights() {
+        let mut chain = Chain::new(2);
+        let land = chain.add_source("land", 0.7);
+        let other = chain.add_source("other", 0.3);
+        // the voices only blend where they share a prefix, here "and"
+        chain.train_source(land, &tokens::words("and fear and fear and fear and fear and"));
+        chain.train_source(other, &tokens::words("and joy and joy and"));
+        assert_eq!(chain.sources()[land].ngrams, 8);
+        assert_eq!(chain.sources()[other].ngrams, 4);
+
+        let spans = chain.generate_traced(10000, &mut Rng::new(7));
+        let words_from = |source| {
+            spans.iter().filter(|s| s.source == source).map(|s| s.text.split(' ').count()).sum::<usize>()
+        };
+        let share = words_from(land) as f64 / 10000.0;
+        assert!((share - 0.7).abs() < 0.05, "{}", share);
+        for span in &spans {
+            let other_word = if span.source == land { "joy" } else { "fear" };
+            assert!(!span.text.contains(other_word), "{:?}", span);
+        }
+    }
+
+    #[test]
+    fn zero_weight_sources_are_never_generated() {
+        let mut chain = Chain::new(2);
+        let muted = chain.add_source("muted", 0.0);
+        let heard = chain.add_source("heard", 1.0);
+        chain.train_source(muted, &["joy", "or", "calm"]);
+        chain.train_source(heard, &["nausea", "and", "fear"]);
+        let spans = chain.generate_traced(30, &mut Rng::new(1));
+        assert!(spans.iter().all(|span| span.source == heard));
+        assert_eq!(chain.generate(5, &mut Rng::new(1)).split(' ').count(), 5);
+    }
+
+    #[test]
+    fn huge_weights_still_blend() {
+        let mut chain = Chain::new(2);
+        let land = chain.add_source("land", 1e308);
+        let other = chain.add_source("other", 1e308);
+        chain.train_source(land, &tokens::words("and fear and fear and"));
+        chain.train_source(other, &tokens::words("and joy and joy and"));
+        let spans = chain.generate_traced(200, &mut Rng::new(1));
+        assert!(spans.iter().any(|span| span.source == land) && spans.iter().any(|span| span.source == other));
+
+        chain.set_sampling(Sampling { temperature: 0.5, ..Default::default() });
+        assert_eq!(chain.generate(5, &mut Rng::new(1)).split(' ').count(), 5);
+    }
+
+    #[test]
+    #[should_panic(expected = "finite")]
+    fn infinite_weights_are_refused() {
+        Chain::new(2).add_source("land", f64::INFINITY);
+    }
+
+    #[test]
+    fn tokenize_keeps_punctuation() {
+        assert_eq!(
+            tokens::tokenize("Land’s labyrinth... is it? (self-destruct, ‘no’)"),
+            vec!["Land’s", "labyrinth", "...", "is", "it", "?", "(", "self-destruct", ",", "‘", "no", "’", ")"]
+        );
+        assert_eq!(
+            tokens::tokenize("only nausea.\n\n\nand fear -\nthere"),
+            vec!["only", "nausea", ".", tokens::PARAGRAPH, "and", "fear", "-", "there"]
+        );
+        assert!(tokens::tokenize("").is_empty());
+        assert!(tokens::tokenize("\n\n  \n").is_empty());
+    }
+
+    #[test]
+    fn detokenize_works() {
+        let tokens = tokens::tokenize("the labyrinth, told. only (fear) ‘is’ there?\n\nit is");
+        assert_eq!(tokens::detokenize(&tokens), "The labyrinth, told. Only (fear) ‘is’ there?\n\nIt is");
+        assert_eq!(tokens::detokenize(&["only", "nausea"]), "Only nausea");
+        assert_eq!(tokens::detokenize::<&str>(&[]), "");
+    }
+
+    #[test]
+    fn compose_sentences() {
+        let text = "Only nausea and fear are there. Fear and nausea are there! Nausea is only fear?";
+        let mut chain = Chain::new(2);
+        chain.train(&tokens::tokenize(text));
+
+        for seed in 0..20 {
+            let composed = chain.compose(Length::Sentences(3), &mut Rng::new(seed));
+            let ends = composed.matches(['.', '!', '?']).count();
+            assert_eq!(ends, 3, "{}", composed);
+            assert!(composed.ends_with(['.', '!', '?']), "{}", composed);
+            assert!(composed.starts_with(char::is_uppercase), "{}", composed);
+        }
+    }
+
+    #[test]
+    fn compose_paragraphs() {
+        let text = "Only nausea. And fear.\n\nFear and nausea.\n\nNausea is only fear.";
+        let mut chain = Chain::new(3);
+        chain.train(&tokens::tokenize(text));
+        let composed = chain.compose(Length::Paragraphs(2), &mut Rng::new(4));
+        assert_eq!(composed.matches("\n\n").count(), 1, "{}", composed);
+        assert!(composed.ends_with('.'), "{}", composed);
+    }
+
+    #[test]
+    fn walks_never_open_on_a_paragraph_break() {
+        // the sentence start "." is always followed by a paragraph break
+        let mut chain = Chain::new(2);
+        chain.train(&tokens::tokenize("Only fear.\n\nOnly fear."));
+        for seed in 0..20 {
+            let composed = chain.compose(Length::Paragraphs(1), &mut Rng::new(seed));
+            assert!(composed.starts_with("Only fear."), "{}", composed);
+            let mut rng = Rng::new(seed);
+            let words: Vec<_> = chain.walk::<&str>(&[], true, Length::Words(5), None, &mut rng).collect();
+            assert_eq!(words.len(), 5);
+            assert_ne!(words[0].0, tokens::PARAGRAPH);
+        }
+    }
+
+    #[test]
+    fn compose_without_sentence_ends_stops() {
+        let mut chain = Chain::new(2);
+        chain.train(&["only", "nausea", "only", "fear"]);
+        let composed = chain.compose(Length::Sentences(1), &mut Rng::new(0));
+        assert!(!composed.is_empty());
+        assert_eq!(chain.compose(Length::Words(5), &mut Rng::new(0)).split(' ').count(), 5);
+    }
+
+    #[test]
+    fn chars_works() {
+        assert_eq!(tokens::chars("Uttunul"), vec!["u", "t", "t", "u", "n", "u", "l"]);
+        assert!(tokens::chars("").is_empty());
+    }
+
+    #[test]
+    fn train_chars_pads_words() {
+        let mut chain = Chain::chars(3);
+        let source = chain.add_source("demons", 1.0);
+        chain.train_chars(source, &["Lurgo", "", "Duoddod"]);
+        assert_eq!(chain.level(), Level::Char);
+        assert!(chain.knows("lurgo") && chain.knows("DUODDOD"));
+        assert!(!chain.knows("lur"));
+
+        let boundary = chain.id(tokens::BOUNDARY).unwrap();
+        let start = chain.state(&[boundary, boundary]).unwrap();
+        assert_eq!(start.total, 2);
+        // "go " ends lurgo, so the last two letters are followed by the boundary
+        let end = chain.state(&[chain.id("g").unwrap(), chain.id("o").unwrap()]).unwrap();
+        assert_eq!(end.next[0].id, boundary);
+        // a trigram per letter, plus the one that ends each word
+        assert_eq!(chain.sources()[0].ngrams, 6 + 8);
+    }
+
+    #[test]
+    fn coin_words_are_new() {
+        let lexicon = ["Lurgo", "Legba", "Duoddod", "Doogu", "Ixix", "Ixigool", "Ixidod", "Krako", "Sukugool", "Skoodu",
+            "Skarkix", "Tchu", "Djynxx", "Katak", "Tzikvik", "Uttunul", "Murrumur", "Oddubb", "Pabbakis", "Ababbatok"];
+        let mut chain = Chain::chars(3);
+        let source = chain.add_source("demons", 1.0);
+        chain.train_chars(source, &lexicon);
+
+        let coined = chain.coin_words(10, 3, 12, &mut Rng::new(7));
+        assert!(!coined.is_empty());
+        for word in &coined {
+            assert!(!chain.knows(word), "{}", word);
+            assert!((3..=12).contains(&word.chars().count()), "{}", word);
+            assert!(word.chars().all(char::is_lowercase), "{}", word);
+        }
+        assert_eq!(coined, chain.coin_words(10, 3, 12, &mut Rng::new(7)));
+
+        // a chain that only knows one word can only ever spell it
+        let mut chain = Chain::chars(2);
+        let source = chain.add_source("one", 1.0);
+        chain.train_chars(source, &["abc"]);
+        assert_eq!(chain.coin(10, &mut Rng::new(1)), Some("abc".to_string()));
+        assert!(chain.coin_words(5, 1, 10, &mut Rng::new(1)).is_empty());
+        assert_eq!(Chain::chars(2).coin(10, &mut Rng::new(1)), None);
+    }
+
+    #[test]
+    fn generate_from_start() {
+        let corpus = tokens::words("only nausea and fear are there only fear and nausea are there and nausea is only fear");
+        let mut chain = Chain::new(2);
+        chain.train(&corpus);
+        let generated = chain.generate_from(&["is"], 5, None, &mut Rng::new(1));
+        assert!(generated.starts_with("is only "), "{}", generated);
+        assert_eq!(generated.split(' ').count(), 6);
+
+        // only the last word is known, so the walk carries on from a state ending with it
+        let mut chain = Chain::new(3);
+        chain.train(&corpus);
+        for seed in 0..10 {
+            let generated = chain.generate_from(&["the", "xyzzy", "and"], 4, None, &mut Rng::new(seed));
+            let next = generated.split(' ').nth(3).unwrap();
+            assert!(next == "fear" || next == "nausea", "{}", generated);
+        }
+        let generated = chain.generate_from(&["xyzzy"], 4, None, &mut Rng::new(1));
+        assert_eq!(generated.split(' ').count(), 5);
+    }
+
+    #[test]
+    fn generate_ends_on() {
+        let mut chain = Chain::new(2);
+        chain.train(&tokens::words("only nausea and fear are there only fear and nausea are there and nausea is only fear"));
+        let there = EndOn::Word("THERE".to_string());
+        let generated = chain.generate_from(&["only"], 50, Some(&there), &mut Rng::new(5));
+        assert!(generated.ends_with(" there"), "{}", generated);
+        assert_eq!(generated.matches("there").count(), 1);
+
+        let text = "The labyrinth is dark. The labyrinth is deep! It is dark and it is deep.";
+        let mut chain = Chain::new(3);
+        chain.train(&tokens::tokenize(text));
+        for seed in 0..10 {
+            let composed = chain.compose_from(&["the", "labyrinth"], Length::Words(100), Some(&EndOn::SentenceEnd), &mut Rng::new(seed));
+            assert!(composed.starts_with("The labyrinth is "), "{}", composed);
+            assert_eq!(composed.matches(['.', '!']).count(), 1, "{}", composed);
+        }
+        assert_eq!("sentence".parse::<EndOn>().unwrap(), EndOn::SentenceEnd);
+        assert_eq!("abyss".parse::<EndOn>().unwrap(), EndOn::Word("abyss".to_string()));
+    }
+
+    #[test]
+    fn gematria_values_match_aq() {
+        assert_eq!(gematria::value("aok"), 54);
+        assert_eq!(gematria::value("A.O.K."), 54);
+        assert_eq!(gematria::reduce("aok"), 9);
+    }
+
+    #[test]
+    fn search_hits_target() {
+        let mut chain = Chain::new(2);
+        chain.train(&tokens::words("only nausea and fear are there only fear and nausea are there and nausea is only fear"));
+        let search = gematria::Search::default();
+
+        let target = gematria::value("only fear and nausea");
+        let found = chain.search(gematria::Target::Value(target), &search, 5, &mut Rng::new(1));
+        assert!(!found.is_empty());
+        for phrase in &found {
+            assert_eq!(gematria::value(phrase), target, "{}", phrase);
+            assert!(phrase.split(' ').count() >= 2, "{}", phrase);
+        }
+        assert_eq!(found, chain.search(gematria::Target::Value(target), &search, 5, &mut Rng::new(1)));
+
+        let found = chain.search(gematria::Target::Reduces(6), &search, 10, &mut Rng::new(2));
+        assert_eq!(found.len(), 10);
+        assert!(found.iter().all(|phrase| gematria::reduce(phrase) == 6));
+
+        // every letter is worth at least 10
+        assert!(chain.search(gematria::Target::Value(9), &search, 5, &mut Rng::new(1)).is_empty());
+    }
+
+    // what followed "a" in 50 walks from it
+    fn after_a(chain: &Chain) -> Vec<String> {
+        (0..50).map(|seed| chain.generate_from(&["a"], 1, None, &mut Rng::new(seed)).replace("a ", "")).collect()
+    }
+
+    #[test]
+    fn sampling_shapes_the_odds() {
+        let mut chain = Chain::new(2);
+        chain.train(&["a", "b", "a", "b", "a", "b", "a", "c", "a", "d"]);
+        let seen = after_a(&chain);
+        assert!(seen.contains(&"c".to_string()) && seen.contains(&"b".to_string()));
+
+        for sampling in [
+            Sampling { temperature: 0.0, ..Default::default() },
+            Sampling { top_k: Some(1), ..Default::default() },
+            Sampling { top_p: Some(0.5), ..Default::default() },
+        ] {
+            chain.set_sampling(sampling);
+            assert!(after_a(&chain).iter().all(|next| next == "b"), "{:?}", sampling);
+        }
+
+        chain.set_sampling(Sampling { top_k: Some(2), ..Default::default() });
+        assert!(after_a(&chain).iter().all(|next| next == "b" || next == "c"));
+
+        // a very high temperature makes the rare words about as likely as the common one
+        chain.set_sampling(Sampling { temperature: 100.0, ..Default::default() });
+        let seen = after_a(&chain);
+        assert!(seen.iter().filter(|&next| next == "b").count() < 30);
+
+        // a very low one picks the most likely word, even when every odd raised to 1 / T underflows
+        let mut chain = Chain::new(2);
+        chain.train(&["a", "b", "a", "b", "a", "c", "a", "d", "a", "e", "a", "f", "a", "g"]);
+        chain.set_sampling(Sampling { temperature: 1e-3, ..Default::default() });
+        assert!(after_a(&chain).iter().all(|next| next == "b"));
+    }
+
+    #[test]
+    fn backoff_carries_on_from_shorter_prefixes() {
+        let mut chain = Chain::new(3);
+        chain.train(&["y", "c", "b", "a", "y"]);
+        let id = |word| chain.id(word).unwrap();
+        assert_eq!(chain.state(&[id("y")]).unwrap().next[0].id, id("c"));
+        assert_eq!(chain.state(&[]).unwrap().total, 5);
+        assert!(chain.state(&[id("a"), id("y")]).is_none());
+
+        // "a y" is a dead end, but "y" was followed by "c", so there's no need to start over
+        chain.set_sampling(Sampling { backoff: true, ..Default::default() });
+        let pairs = ["y c", "c b", "b a", "a y"];
+        for seed in 0..10 {
+            let generated = chain.generate(30, &mut Rng::new(seed));
+            let words: Vec<&str> = generated.split(' ').collect();
+            assert_eq!(words.len(), 30);
+            for pair in words.windows(2) {
+                assert!(pairs.contains(&pair.join(" ").as_str()), "{}", generated);
+            }
+        }
+    }
+
+    #[test]
+    fn read_matches_tokenize() {
+        let text = "Only nausea and\nfear are there.\n\n\nOnly fear (and nausea)\n\n";
+        let lines: Vec<Vec<String>> = tokens::read(text.as_bytes(), false).map(Result::unwrap).collect();
+        assert_eq!(lines.concat(), tokens::tokenize(text));
+        let lines: Vec<Vec<String>> = tokens::read(text.as_bytes(), true).map(Result::unwrap).collect();
+        assert_eq!(lines.concat(), tokens::words(text));
+
+        let tokens = tokens::tokenize(text);
+        let mut prose = tokens::Prose::default();
+        let streamed: String = tokens.iter().map(|token| prose.push(token)).collect();
+        assert_eq!(streamed, tokens::detokenize(&tokens));
+        assert_eq!(tokens::detokenize(&["fear", tokens::PARAGRAPH]), "Fear");
+    }
+
+    #[test]
+    fn train_reader_matches_train() {
+        let text = "only nausea and\nfear are there only fear\n\nand nausea are there and nausea is only fear";
+        for plain in [true, false] {
+            let tokens = if plain { tokens::words(text) } else { tokens::tokenize(text) };
+            let mut whole = Chain::new(3);
+            whole.train(&tokens);
+            let mut streamed = Chain::new(3);
+            let source = streamed.add_source("corpus", 1.0);
+            streamed.train_reader(source, text.as_bytes(), plain).unwrap();
+
+            assert_eq!(streamed.vocab(), whole.vocab());
+            assert_eq!(streamed.sources(), whole.sources());
+            let prefixes = |chain: &Chain| chain.states().iter().map(|s| (s.prefix.clone(), s.next.clone())).collect::<Vec<_>>();
+            assert_eq!(prefixes(&streamed), prefixes(&whole));
+            assert_eq!(streamed.state(&[]).unwrap().total, whole.state(&[]).unwrap().total);
+            assert_eq!(streamed.generate(30, &mut Rng::new(9)), whole.generate(30, &mut Rng::new(9)));
+        }
+
+        let mut chain = Chain::chars(3);
+        let source = chain.add_source("demons", 1.0);
+        chain.train_reader(source, "Lurgo, Legba\nDuoddod".as_bytes(), false).unwrap();
+        assert!(chain.knows("legba") && chain.knows("duoddod"));
+    }
+
+    #[test]
+    fn walk_streams_words() {
+        let mut chain = Chain::new(2);
+        chain.train(&tokens::words("only nausea and fear are there only fear and nausea are there and nausea is only fear"));
+        let mut rng = Rng::new(666);
+        let walked: Vec<&str> = chain.walk::<&str>(&[], false, Length::Words(12), None, &mut rng).map(|(word, _)| word).collect();
+        assert_eq!(walked.join(" "), chain.generate(12, &mut Rng::new(666)));
+
+        // nothing is kept, so a long walk costs no more memory than a short one
+        let mut rng = Rng::new(1);
+        assert_eq!(chain.walk::<&str>(&[], false, Length::Words(100_000), None, &mut rng).count(), 100_000);
+        assert_eq!(Chain::new(2).walk::<&str>(&[], false, Length::Words(10), None, &mut rng).count(), 0);
+    }
+
+    #[test]
+    fn stats_works() {
+        let mut chain = Chain::new(2);
+        chain.train(&["a", "b", "a", "c", "a", "b"]);
+        let stats = chain.stats();
+        assert_eq!((stats.order, stats.vocab, stats.states, stats.ngrams), (2, 3, 3, 5));
+        // "a" is followed by b twice and c once, "b" and "c" only by "a"
+        let a = chain.state(&[chain.id("a").unwrap()]).unwrap();
+        let h = -(2.0 / 3.0 * (2.0f64 / 3.0).log2() + 1.0 / 3.0 * (1.0f64 / 3.0).log2());
+        assert!((chain.entropy(a) - h).abs() < 1e-9);
+        assert!((stats.max_entropy - h).abs() < 1e-9);
+        assert!((stats.entropy - h * 3.0 / 5.0).abs() < 1e-9);
+        assert!((stats.deterministic - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(Chain::new(2).stats().entropy, 0.0);
+    }
+
+    #[test]
+    fn continuations_works() {
+        let mut chain = Chain::new(3);
+        chain.train(&["a", "b", "a", "c", "a", "b", "a", "b"]);
+        let next = chain.continuations(&["b", "a"], 5);
+        assert_eq!(next.iter().map(|&(word, _)| word).collect::<Vec<_>>(), vec!["c", "b"]);
+        assert!((next[0].1 - 0.5).abs() < 1e-9);
+        assert_eq!(chain.continuations(&["b", "a"], 1).len(), 1);
+
+        // "x" is unknown and "c a" was only followed by "b", "a" alone backs off to the bigrams
+        assert_eq!(chain.continuations(&["x", "c", "a"], 5), vec![("b", 1.0)]);
+        let after_a = chain.continuations(&["x", "a"], 5);
+        assert_eq!(after_a[0].0, "b");
+        assert!((after_a[0].1 - 0.75).abs() < 1e-9);
+        assert_eq!(chain.continuations::<&str>(&[], 1)[0].0, "a");
+    }
+
+    #[test]
+    fn perplexity_works() {
+        let text = tokens::words("only nausea and fear are there only fear and nausea are there and nausea is only fear");
+        let scores: Vec<f64> = (1..=3)
+            .map(|order| {
+                let mut chain = Chain::new(order);
+                chain.train(&text);
+                chain.perplexity(&text).perplexity
+            })
+            .collect();
+        // higher orders fit the text they were trained on better
+        assert!(scores[0] > scores[1] && scores[1] > scores[2], "{:?}", scores);
+
+        let mut chain = Chain::new(2);
+        chain.train(&["a", "b", "a", "b"]);
+        let p = chain.perplexity(&["a", "b", "a", "xyzzy", "b"]);
+        assert_eq!((p.scored, p.unknown), (4, 1));
+        assert!(p.perplexity >= 1.0);
+        assert_eq!(chain.perplexity::<&str>(&[]).perplexity, f64::INFINITY);
+
+        let mut chain = Chain::chars(3);
+        let source = chain.add_source("demons", 1.0);
+        chain.train_chars(source, &["lurgo", "legba"]);
+        let p = chain.perplexity(&["lurgo", "lurgx"]);
+        assert_eq!((p.scored, p.unknown), (11, 1));
+    }
+
+    #[test]
+    fn pick_carries_on_from_the_words_so_far() {
+        let mut chain = Chain::new(3);
+        chain.train(&tokens::tokenize("The labyrinth is dark. The labyrinth is deep! It is dark."));
+        for seed in 0..10 {
+            let mut rng = Rng::new(seed);
+            assert_eq!(chain.pick(&["the", "labyrinth"], &mut rng), Some("is"));
+            let next = chain.pick(&["labyrinth", "is"], &mut rng).unwrap();
+            assert!(next == "dark" || next == "deep", "{}", next);
+            // only the last word is known, so it carries on from a state ending with it
+            assert_eq!(chain.pick(&["xyzzy", "labyrinth"], &mut rng), Some("is"));
+            let first = chain.pick::<&str>(&[], &mut rng).unwrap();
+            assert!(first == "The" || first == "It", "{}", first);
+            assert!(chain.pick(&["xyzzy"], &mut rng).is_some());
+        }
+        assert_eq!(Chain::new(3).pick(&["the"], &mut Rng::new(0)), None);
+    }
+
+    #[test]
+    fn pick_spells_a_word_at_a_time() {
+        let mut chain = Chain::chars(3);
+        let source = chain.add_source("demons", 1.0);
+        chain.train_chars(source, &["lurgo", "legba"]);
+        let lurgo = ["l", "u", "r", "g", "o"];
+        for seed in 0..10 {
+            let mut rng = Rng::new(seed);
+            // a word starts from the boundaries, never from the middle of one
+            assert_eq!(chain.pick::<&str>(&[], &mut rng), Some("l"));
+            assert_eq!(chain.pick(&lurgo, &mut rng), Some(tokens::BOUNDARY));
+            // and after a boundary the next one starts over
+            assert_eq!(chain.pick(&[&lurgo[..], &[tokens::BOUNDARY]].concat(), &mut rng), Some("l"));
+        }
+        assert_eq!(chain.continuations::<&str>(&[], 5), vec![("l", 1.0)]);
+        assert_eq!(chain.continuations(&["l", "u", "r", "g", "o", " ", "l"], 5).len(), 2);
+    }
+}
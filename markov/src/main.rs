@@ -1,39 +1,449 @@
-use std::fs;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
 use structopt::StructOpt;
 use anyhow::{Result};
+use markov::gematria::{Search, Target};
+use markov::tokens::{self, Prose};
+use markov::{model, Chain, EndOn, Length, Level, Rng, Sampling};
 
-#[cxx::bridge]
-mod ffi {
-    unsafe extern "C++" {
-        include!("markov/src/markov.h"); 
-        fn markovn(text: &CxxString, words: i32, N: i32) -> UniquePtr<CxxString>;
+// a corpus on the command line, `path` or `path:weight`
+// a directory is one corpus made of every file in it
+#[derive(Debug)]
+struct Corpus {
+    path: PathBuf,
+    weight: f64,
+}
+
+impl FromStr for Corpus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Some((path, weight)) = s.rsplit_once(':') {
+            if let Ok(weight) = weight.parse::<f64>() {
+                anyhow::ensure!(weight.is_finite() && weight >= 0.0, "weight of {} must be finite and not negative", path);
+                return Ok(Corpus { path: PathBuf::from(path), weight });
+            }
+        }
+        Ok(Corpus { path: PathBuf::from(s), weight: 1.0 })
+    }
+}
+
+impl Corpus {
+    fn name(&self) -> String {
+        match self.path.file_stem() {
+            Some(stem) => stem.to_string_lossy().into_owned(),
+            None => self.path.display().to_string(),
+        }
+    }
+
+    fn files(&self) -> Result<Vec<PathBuf>> {
+        if !self.path.is_dir() {
+            return Ok(vec![self.path.clone()]);
+        }
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&self.path)? {
+            let path = entry?.path();
+            if path.is_file() {
+                files.push(path);
+            }
+        }
+        files.sort();
+        Ok(files)
     }
 }
 
 #[derive(StructOpt, Debug)]
-struct Cli {
+enum Cli {
+    // count the ngrams of one or more corpora and save them as a model
+    Train {
+        // files or directories to read, each optionally weighted as path:weight
+        #[structopt(required = true)]
+        corpora: Vec<Corpus>,
+
+        // where to save the model
+        #[structopt(short, long, parse(from_os_str))]
+        output: PathBuf,
+
+        // markov ngram
+        #[structopt(long, default_value = "4")]
+        ngram: usize,
+
+        // strip punctuation and digits instead of keeping them as tokens
+        #[structopt(long)]
+        plain: bool,
+
+        // learn the letters of each word instead of the words of each sentence
+        #[structopt(long)]
+        chars: bool,
+    },
+
+    // describe a saved model, or one trained straight from one or more corpora
+    Stats {
+        // a model from `markov train`, or files and directories as in train
+        #[structopt(required = true)]
+        paths: Vec<Corpus>,
+
+        // markov ngram, when training from text files
+        #[structopt(long, default_value = "4")]
+        ngram: usize,
+
+        // strip punctuation and digits, when training from text files and reading held-out text
+        #[structopt(long)]
+        plain: bool,
+
+        // learn the letters of each word, when training from text files
+        #[structopt(long)]
+        chars: bool,
+
+        // list the likeliest words after this phrase
+        #[structopt(long)]
+        prefix: Option<String>,
+
+        // the number of words to list after --prefix
+        #[structopt(long, default_value = "10")]
+        top: usize,
+
+        // score held-out text the model wasn't trained on, lower is better
+        #[structopt(long, parse(from_os_str))]
+        perplexity: Option<PathBuf>,
+    },
+
+    // write with the model a word at a time, picking from its likeliest next words
+    Repl {
+        // a model from `markov train`, or files and directories as in train
+        #[structopt(required = true)]
+        paths: Vec<Corpus>,
+
+        // markov ngram, when training from text files
+        #[structopt(long, default_value = "4")]
+        ngram: usize,
+
+        // strip punctuation and digits, when training from text files and reading what you type
+        #[structopt(long)]
+        plain: bool,
+
+        // learn the letters of each word, when training from text files
+        #[structopt(long)]
+        chars: bool,
+
+        // the number of next words to offer
+        #[structopt(long, default_value = "9")]
+        top: usize,
+
+        // seed for the words the model picks
+        #[structopt(long)]
+        seed: Option<u64>,
+
+        // the text to begin with
+        #[structopt(long)]
+        start: Option<String>,
+    },
+
+    // generate text from a saved model, or straight from one or more corpora
+    Generate {
+        // a model from `markov train`, or files and directories as in train
+        #[structopt(required = true)]
+        paths: Vec<Corpus>,
+
+        // the number of words to generate, or to coin with a character-level model
+        #[structopt(short, long, default_value = "100")]
+        words: usize,
+
+        // generate whole sentences instead of a number of words, plain text has no sentences to count
+        #[structopt(long, conflicts_with_all = &["paragraphs", "plain"])]
+        sentences: Option<usize>,
+
+        // generate whole paragraphs instead of a number of words
+        #[structopt(long, conflicts_with = "plain")]
+        paragraphs: Option<usize>,
+
+        // markov ngram, when training from text files
+        #[structopt(long, default_value = "4")]
+        ngram: usize,
+
+        // strip punctuation and digits, when training from text files
+        #[structopt(long)]
+        plain: bool,
+
+        // coin new words from the letters of the corpus, when training from text files
+        #[structopt(long)]
+        chars: bool,
+
+        // the shortest word to coin
+        #[structopt(long, default_value = "3")]
+        min_len: usize,
+
+        // the longest word to coin
+        #[structopt(long, default_value = "12")]
+        max_len: usize,
 
-    // the path of the file to read
-    #[structopt(parse(from_os_str))]
-    path: std::path::PathBuf,
+        // seed for the generator, the same seed always gives the same text
+        #[structopt(long)]
+        seed: Option<u64>,
 
-    // the number of words to generate
-    #[structopt(default_value = "100")]
-    word_count: i32,
+        // below 1 favours the likeliest words, above 1 flattens the odds, 0 always picks the likeliest
+        #[structopt(long, default_value = "1.0")]
+        temperature: f64,
 
-    // markov ngram
-    #[structopt(default_value = "4")]
-    ngram: i32,
+        // only pick from the k likeliest next words
+        #[structopt(long)]
+        top_k: Option<usize>,
+
+        // only pick from the likeliest next words that make up this much of the probability
+        #[structopt(long)]
+        top_p: Option<f64>,
+
+        // at a dead end, carry on from a shorter prefix instead of starting over
+        #[structopt(long)]
+        backoff: bool,
+
+        // print the source of each span of the output
+        #[structopt(long, conflicts_with_all = &["start", "end-on"])]
+        trace: bool,
+
+        // begin with this phrase, carrying on from the state nearest to its end
+        #[structopt(long)]
+        start: Option<String>,
+
+        // stop early at the end of a sentence ("sentence") or at a target word
+        #[structopt(long)]
+        end_on: Option<EndOn>,
+
+        // search for phrases with this AQ value
+        #[structopt(long, conflicts_with_all = &["reduces-to", "trace", "start"])]
+        aq: Option<i32>,
+
+        // search for phrases whose AQ reduction ends in this digit
+        #[structopt(long, conflicts_with_all = &["trace", "start"])]
+        reduces_to: Option<i32>,
+
+        // the number of phrases to find with --aq or --reduces-to
+        #[structopt(long, default_value = "10")]
+        phrases: usize,
+
+        // phrases kept after each word of the search
+        #[structopt(long, default_value = "256")]
+        beam: usize,
+
+        // the longest phrase to search for
+        #[structopt(long, default_value = "12")]
+        max_words: usize,
+    },
+}
+
+const REPL_HELP: &str = "a number picks that word, enter lets the model pick, anything else is added as typed
+-  takes back the last word
+q  quits";
+
+// words typed at the prompt, split the way the model was trained
+fn split(chain: &Chain, plain: bool, text: &str) -> Vec<String> {
+    match chain.level() {
+        Level::Char => tokens::chars(text),
+        Level::Word if plain => tokens::words(text),
+        Level::Word => tokens::tokenize(text),
+    }
+}
+
+fn render(chain: &Chain, words: &[String]) -> String {
+    match chain.level() {
+        Level::Char => words.concat(),
+        Level::Word => tokens::detokenize(words),
+    }
+}
+
+// a cut-up session, like aq's prompt: shows the text so far and the likeliest next words
+fn start_prompt(chain: &Chain, mut words: Vec<String>, top: usize, plain: bool, rng: &mut Rng) -> Result<()> {
+    println!("markov\n{}", REPL_HELP);
+    let stdin = io::stdin();
+    let mut buffer = String::new();
+
+    loop {
+        let candidates = chain.continuations(&words, top);
+        println!("\n{}", render(chain, &words));
+        for (i, (word, p)) in candidates.iter().enumerate() {
+            let word = match *word {
+                tokens::BOUNDARY => "(end of word)",
+                word => word,
+            };
+            println!("{:>3}  {:.4}  {}", i + 1, p, word);
+        }
+
+        print!("> ");
+        io::stdout().flush()?;
+        buffer.clear();
+        if stdin.read_line(&mut buffer)? == 0 {
+            break;
+        }
+        let input = buffer.trim();
+        match input {
+            "q" | "Q" => break,
+            "-" => {
+                words.pop();
+            }
+            "" => match chain.pick(&words, rng) {
+                Some(word) => words.push(word.to_string()),
+                None => println!("the model has nothing to say"),
+            },
+            _ => match input.parse::<usize>() {
+                Ok(n) if (1..=candidates.len()).contains(&n) => words.push(candidates[n - 1].0.to_string()),
+                _ => words.extend(split(chain, plain, input)),
+            },
+        }
+    }
+
+    println!("\n{}", render(chain, &words));
+    Ok(())
+}
+
+// a single model path is loaded, anything else is trained on
+fn load_or_train(paths: &[Corpus], ngram: usize, plain: bool, chars: bool) -> Result<Chain> {
+    match paths {
+        [only] if model::is_model(&only.path) => model::load_file(&only.path),
+        _ => train(paths, ngram, plain, chars),
+    }
+}
+
+fn train(corpora: &[Corpus], ngram: usize, plain: bool, chars: bool) -> Result<Chain> {
+    anyhow::ensure!(ngram > 0, "ngram must be at least 1");
+    let mut chain = match chars {
+        true => Chain::chars(ngram),
+        false => Chain::new(ngram),
+    };
+    for corpus in corpora {
+        let source = chain.add_source(&corpus.name(), corpus.weight);
+        for file in corpus.files()? {
+            chain.train_reader(source, BufReader::new(File::open(&file)?), plain)?;
+        }
+    }
+    Ok(chain)
 }
 
 // Run NLG on a text file and output the results
 fn main() -> Result<()> {
-    let args = Cli::from_args();
-    let text = fs::read_to_string(args.path)?;
+    match Cli::from_args() {
+        Cli::Train { corpora, output, ngram, plain, chars } => {
+            let chain = train(&corpora, ngram, plain, chars)?;
+            model::save_file(&chain, &output)?;
+            eprintln!("{} words, {} states -> {}", chain.vocab().len(), chain.states().len(), output.display());
+        }
+        Cli::Stats { paths, ngram, plain, chars, prefix, top, perplexity } => {
+            let chain = load_or_train(&paths, ngram, plain, chars)?;
+            let tokenize = |text: &str| match plain || chain.level() == Level::Char {
+                true => tokens::words(text),
+                false => tokens::tokenize(text),
+            };
 
-    cxx::let_cxx_string!(cxx_text = text);
-    let generated = ffi::markovn(&cxx_text, args.word_count, args.ngram);
-    println!("{}", *generated);
+            let stats = chain.stats();
+            println!("order          {}", stats.order);
+            println!("vocabulary     {}", stats.vocab);
+            println!("states         {}", stats.states);
+            println!("ngrams         {}", stats.ngrams);
+            println!("entropy        {:.3} bits per word, {:.3} at most", stats.entropy, stats.max_entropy);
+            println!("deterministic  {:.1}% of states", stats.deterministic * 100.0);
+
+            if let Some(prefix) = prefix {
+                println!("\nafter \"{}\"", prefix);
+                for (word, p) in chain.continuations(&tokenize(&prefix), top) {
+                    println!("{:>8.4}  {}", p, word);
+                }
+            }
+            if let Some(path) = perplexity {
+                let p = chain.perplexity(&tokenize(&fs::read_to_string(&path)?));
+                println!("\nperplexity     {:.2} over {} tokens of {}, {} unknown", p.perplexity, p.scored, path.display(), p.unknown);
+            }
+        }
+        Cli::Repl { paths, ngram, plain, chars, top, seed, start } => {
+            let chain = load_or_train(&paths, ngram, plain, chars)?;
+            let mut rng = match seed {
+                Some(seed) => Rng::new(seed),
+                None => Rng::from_time(),
+            };
+            let words = split(&chain, plain, start.as_deref().unwrap_or_default());
+            start_prompt(&chain, words, top, plain, &mut rng)?;
+        }
+        Cli::Generate {
+            paths,
+            words,
+            sentences,
+            paragraphs,
+            ngram,
+            plain,
+            chars,
+            min_len,
+            max_len,
+            seed,
+            temperature,
+            top_k,
+            top_p,
+            backoff,
+            trace,
+            start,
+            end_on,
+            aq,
+            reduces_to,
+            phrases,
+            beam,
+            max_words,
+        } => {
+            let mut chain = load_or_train(&paths, ngram, plain, chars)?;
+            anyhow::ensure!(temperature >= 0.0, "temperature must not be negative");
+            if let Some(digit) = reduces_to {
+                anyhow::ensure!((1..=9).contains(&digit), "--reduces-to takes a digit from 1 to 9, not {}", digit);
+            }
+            chain.set_sampling(Sampling { temperature, top_k, top_p, backoff });
+            let start = match start {
+                Some(phrase) if plain => markov::tokens::words(&phrase),
+                Some(phrase) => markov::tokens::tokenize(&phrase),
+                None => Vec::new(),
+            };
+            let mut rng = match seed {
+                Some(seed) => Rng::new(seed),
+                None => Rng::from_time(),
+            };
+
+            let target = match (aq, reduces_to) {
+                (Some(value), _) => Some(Target::Value(value)),
+                (_, Some(digit)) => Some(Target::Reduces(digit)),
+                _ => None,
+            };
+
+            if let Some(target) = target {
+                let search = Search { beam, max_words, ..Default::default() };
+                for phrase in chain.search(target, &search, phrases, &mut rng) {
+                    println!("{}", phrase);
+                }
+            } else if chain.level() == Level::Char {
+                // one per line, ready to pipe into aq or prim
+                for word in chain.coin_words(words, min_len, max_len, &mut rng) {
+                    println!("{}", word);
+                }
+            } else if trace {
+                for span in chain.generate_traced(words, &mut rng) {
+                    println!("[{}] {}", chain.sources()[span.source].name, span.text);
+                }
+            } else {
+                // written as it is generated, so any amount of text fits in memory
+                let mut out = BufWriter::new(io::stdout().lock());
+                let length = match (sentences, paragraphs) {
+                    (Some(n), _) => Length::Sentences(n),
+                    (_, Some(n)) => Length::Paragraphs(n),
+                    _ => Length::Words(words),
+                };
+                let walk = chain.walk(&start, !plain, length, end_on.as_ref(), &mut rng).map(|(word, _)| word);
+                let mut prose = Prose::default();
+                for (i, word) in start.iter().map(String::as_str).chain(walk).enumerate() {
+                    match plain {
+                        true if i > 0 => write!(out, " {}", word)?,
+                        true => write!(out, "{}", word)?,
+                        false => write!(out, "{}", prose.push(word))?,
+                    }
+                }
+                writeln!(out)?;
+            }
+        }
+    }
 
     Ok(())
 }
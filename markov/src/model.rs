@@ -0,0 +1,49 @@
+// saving and loading trained chains, so a corpus only has to be tokenized once
+// a model is MAGIC, then VERSION as a little-endian u32, then the bincode-encoded chain
+use crate::chain::Chain;
+use anyhow::{ensure, Result};
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"MRKV";
+// bump whenever the layout of Chain changes
+pub const VERSION: u32 = 4;
+
+pub fn save<W: Write>(chain: &Chain, mut writer: W) -> Result<()> {
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    bincode::serialize_into(&mut writer, chain)?;
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn load<R: Read>(mut reader: R) -> Result<Chain> {
+    let mut header = [0u8; 8];
+    reader.read_exact(&mut header)?;
+    ensure!(&header[..4] == MAGIC, "not a markov model");
+    let version = u32::from_le_bytes(header[4..].try_into().unwrap());
+    ensure!(version == VERSION, "model version {} is not supported, expected {}", version, VERSION);
+
+    let mut chain: Chain = bincode::deserialize_from(reader)?;
+    chain.reindex();
+    Ok(chain)
+}
+
+pub fn save_file<P: AsRef<Path>>(chain: &Chain, path: P) -> Result<()> {
+    save(chain, BufWriter::new(File::create(path)?))
+}
+
+pub fn load_file<P: AsRef<Path>>(path: P) -> Result<Chain> {
+    load(BufReader::new(File::open(path)?))
+}
+
+// whether path starts like a model, rather than a text corpus
+pub fn is_model<P: AsRef<Path>>(path: P) -> bool {
+    let mut magic = [0u8; 4];
+    match File::open(path) {
+        Ok(mut file) => file.read_exact(&mut magic).is_ok() && &magic == MAGIC,
+        Err(_) => false,
+    }
+}
@@ -0,0 +1,144 @@
+// what a trained chain looks like from the inside, for tuning the ngram order
+use crate::chain::{Chain, Level, State};
+use crate::tokens;
+
+// shorter prefixes count for less, as in stupid backoff (Brants et al., 2007)
+const BACKOFF: f64 = 0.4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stats {
+    pub order: usize,
+    pub vocab: usize,
+    pub states: usize,
+    pub ngrams: u64,
+    // bits of choice at each step of a walk, the mean over states weighted by how often each was seen
+    pub entropy: f64,
+    pub max_entropy: f64,
+    // the share of states that can only ever be followed by one word, which just copy the corpus
+    pub deterministic: f64,
+}
+
+// how surprised a chain is by some text, lower is better
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Perplexity {
+    pub perplexity: f64,
+    pub scored: usize,
+    // words the chain has never seen, left out of the score
+    pub unknown: usize,
+}
+
+impl Chain {
+    pub fn stats(&self) -> Stats {
+        let seen: u64 = self.states().iter().map(|state| state.total as u64).sum();
+        let entropies: Vec<f64> = self.states().iter().map(|state| self.entropy(state)).collect();
+        let weighted: f64 = self.states().iter().zip(&entropies).map(|(state, h)| state.total as f64 * h).sum();
+        let deterministic = entropies.iter().filter(|&&h| h == 0.0).count();
+        Stats {
+            order: self.order(),
+            vocab: self.vocab().len(),
+            states: self.states().len(),
+            ngrams: self.sources().iter().map(|source| source.ngrams).sum(),
+            entropy: if seen > 0 { weighted / seen as f64 } else { 0.0 },
+            max_entropy: entropies.iter().cloned().fold(0.0, f64::max),
+            deterministic: if entropies.is_empty() { 0.0 } else { deterministic as f64 / entropies.len() as f64 },
+        }
+    }
+
+    // bits of choice for the word after state, 0 when there is only one
+    pub fn entropy(&self, state: &State) -> f64 {
+        -self.distribution(state).iter().map(|&(_, p)| p * p.log2()).sum::<f64>()
+    }
+
+    // the likeliest words after prefix and how likely each is, at most count of them
+    // backs off to the longest end of prefix the chain has seen
+    pub fn continuations<S: AsRef<str>>(&self, prefix: &[S], count: usize) -> Vec<(&str, f64)> {
+        let context = self.known_context(prefix);
+        let state = (0..=context.len()).rev().find_map(|k| self.state(&context[context.len() - k..]));
+        let mut next = match state {
+            Some(state) => self.distribution(state),
+            None => Vec::new(),
+        };
+        next.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        next.into_iter().take(count).map(|(id, p)| (self.word(id), p)).collect()
+    }
+
+    // perplexity of held-out tokens, or of words for a character-level chain
+    // unseen n-grams back off to shorter prefixes, so the score is only comparable between chains on the same text
+    pub fn perplexity<S: AsRef<str>>(&self, tokens: &[S]) -> Perplexity {
+        let mut bits = 0.0;
+        let mut scored = 0;
+        let mut unknown = 0;
+        let mut score = |ids: &[Option<u32>], from: usize| {
+            for i in from..ids.len() {
+                let id = match ids[i] {
+                    Some(id) => id,
+                    None => {
+                        unknown += 1;
+                        continue;
+                    }
+                };
+                let context: Vec<u32> = ids[i.saturating_sub(self.order() - 1)..i].iter().rev().map_while(|&id| id).collect();
+                let context: Vec<u32> = context.into_iter().rev().collect();
+                match self.backoff_probability(&context, id) {
+                    p if p > 0.0 => {
+                        bits -= p.log2();
+                        scored += 1;
+                    }
+                    _ => unknown += 1,
+                }
+            }
+        };
+
+        match self.level() {
+            Level::Word => {
+                let ids: Vec<Option<u32>> = tokens.iter().map(|token| self.id(token.as_ref())).collect();
+                score(&ids, 0);
+            }
+            Level::Char => {
+                // each word on its own, from the start of a word to its end
+                let boundary = self.id(tokens::BOUNDARY);
+                for word in tokens {
+                    let mut ids = vec![boundary; self.order() - 1];
+                    ids.extend(tokens::chars(word.as_ref()).iter().map(|ch| self.id(ch)));
+                    ids.push(boundary);
+                    score(&ids, self.order() - 1);
+                }
+            }
+        }
+
+        Perplexity {
+            perplexity: if scored > 0 { (bits / scored as f64).exp2() } else { f64::INFINITY },
+            scored,
+            unknown,
+        }
+    }
+
+    // every word after state with its probability, the same word from several sources counted once
+    fn distribution(&self, state: &State) -> Vec<(u32, f64)> {
+        let mut next: Vec<(u32, f64)> = Vec::new();
+        for successor in &state.next {
+            let p = self.probability(state, successor);
+            match next.iter_mut().find(|(id, _)| *id == successor.id) {
+                Some((_, total)) => *total += p,
+                None => next.push((successor.id, p)),
+            }
+        }
+        next.retain(|&(_, p)| p > 0.0);
+        next
+    }
+
+    // the probability of id after context, from the longest end of context that was followed by it
+    fn backoff_probability(&self, context: &[u32], id: u32) -> f64 {
+        let mut scale = 1.0;
+        for k in (0..=context.len().min(self.order() - 1)).rev() {
+            if let Some(state) = self.state(&context[context.len() - k..]) {
+                let p: f64 = state.next.iter().filter(|s| s.id == id).map(|s| self.probability(state, s)).sum();
+                if p > 0.0 {
+                    return scale * p;
+                }
+            }
+            scale *= BACKOFF;
+        }
+        0.0
+    }
+}
@@ -0,0 +1,180 @@
+// turning a corpus into tokens, and tokens back into text
+use std::io::{self, BufRead};
+
+// stands in for a blank line between paragraphs
+pub const PARAGRAPH: &str = "¶";
+
+// stands in for the edges of a word in character-level chains
+pub const BOUNDARY: &str = " ";
+
+// strips digits and punctuation, then splits on whitespace
+// EX: words("the 3 labyrinth's\ninterior") -> ["the", "labyrinths", "interior"]
+pub fn words(text: &str) -> Vec<String> {
+    let formatted: String = text
+        .chars()
+        .filter(|ch| !(ch.is_ascii_digit() || ch.is_ascii_punctuation()))
+        .collect();
+    formatted.split_whitespace().map(String::from).collect()
+}
+
+// each letter of a word as a token of its own, lowercased
+// EX: chars("Uttunul") -> ["u", "t", "t", "u", "n", "u", "l"]
+pub fn chars(word: &str) -> Vec<String> {
+    word.chars().flat_map(char::to_lowercase).map(String::from).collect()
+}
+
+// apostrophes and hyphens only belong to a word when they join two parts of it
+fn is_joiner(ch: char) -> bool {
+    matches!(ch, '\'' | '’' | '-')
+}
+
+// keeps punctuation as tokens of its own and blank lines as PARAGRAPH
+// runs of the same mark stay together, so "..." is one token
+// EX: tokenize("Land’s labyrinth... is it?") -> ["Land’s", "labyrinth", "...", "is", "it", "?"]
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokenizer = Tokenizer::default();
+    text.lines().flat_map(|line| tokenizer.line(line)).collect()
+}
+
+// tokenize a line at a time, remembering blank lines between them
+#[derive(Debug, Default)]
+pub struct Tokenizer {
+    blank: bool,
+    started: bool,
+}
+
+impl Tokenizer {
+    pub fn line(&mut self, line: &str) -> Vec<String> {
+        let mut tokens: Vec<String> = Vec::new();
+        if line.trim().is_empty() {
+            self.blank = true;
+            return tokens;
+        }
+        if self.blank && self.started {
+            tokens.push(PARAGRAPH.to_string());
+        }
+        self.blank = false;
+        self.started = true;
+
+        let chars: Vec<char> = line.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            let ch = chars[i];
+            let start = i;
+            if ch.is_whitespace() {
+                i += 1;
+                continue;
+            } else if ch.is_alphanumeric() {
+                while i < chars.len()
+                    && (chars[i].is_alphanumeric()
+                        || (is_joiner(chars[i]) && chars.get(i + 1).is_some_and(|c| c.is_alphanumeric())))
+                {
+                    i += 1;
+                }
+            } else {
+                while i < chars.len() && chars[i] == ch {
+                    i += 1;
+                }
+            }
+            tokens.push(chars[start..i].iter().collect());
+        }
+        tokens
+    }
+}
+
+// the tokens of each line of reader, so a corpus never has to be read whole
+// the same as tokenize (or words, if plain) of everything it reads
+pub fn read<R: BufRead>(reader: R, plain: bool) -> impl Iterator<Item = io::Result<Vec<String>>> {
+    let mut tokenizer = Tokenizer::default();
+    reader.lines().map(move |line| {
+        let line = line?;
+        Ok(match plain {
+            true => words(&line),
+            false => tokenizer.line(&line),
+        })
+    })
+}
+
+pub fn is_sentence_end(token: &str) -> bool {
+    !token.is_empty() && token.chars().all(|ch| matches!(ch, '.' | '!' | '?' | '…'))
+}
+
+// no space before these
+fn is_closer(token: &str) -> bool {
+    is_sentence_end(token) || matches!(token, "," | ";" | ":" | ")" | "]" | "}" | "’" | "”" | "'s" | "%")
+}
+
+// no space after these
+fn is_opener(token: &str) -> bool {
+    matches!(token, "(" | "[" | "{" | "‘" | "“")
+}
+
+fn capitalize(token: &str) -> String {
+    let mut chars = token.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+// joins tokens with the spacing of ordinary prose, capitalising the start of every sentence
+// EX: detokenize(["the", "labyrinth", ",", "told", ".", "only", "fear"]) -> "The labyrinth, told. Only fear"
+pub fn detokenize<S: AsRef<str>>(tokens: &[S]) -> String {
+    let mut prose = Prose::default();
+    tokens.iter().map(|token| prose.push(token.as_ref())).collect()
+}
+
+// detokenize a token at a time, for text too long to hold at once
+#[derive(Debug)]
+pub struct Prose {
+    sentence_start: bool,
+    space: bool,
+    // a paragraph break waits for the text after it, so the output never ends on one
+    paragraph: bool,
+    started: bool,
+}
+
+impl Default for Prose {
+    fn default() -> Self {
+        Prose {
+            sentence_start: true,
+            space: false,
+            paragraph: false,
+            started: false,
+        }
+    }
+}
+
+impl Prose {
+    // the text that token adds
+    pub fn push(&mut self, token: &str) -> String {
+        let mut text = String::new();
+        if token == PARAGRAPH {
+            self.paragraph = self.started;
+            self.sentence_start = true;
+            self.space = false;
+            return text;
+        }
+
+        if self.paragraph {
+            text.push_str("\n\n");
+            self.paragraph = false;
+        }
+        if self.space && !is_closer(token) {
+            text.push(' ');
+        }
+        if self.sentence_start && token.chars().any(char::is_alphanumeric) {
+            text.push_str(&capitalize(token));
+            self.sentence_start = false;
+        } else {
+            text.push_str(token);
+        }
+
+        if is_sentence_end(token) {
+            self.sentence_start = true;
+        }
+        self.space = !is_opener(token);
+        self.started = true;
+        text
+    }
+}
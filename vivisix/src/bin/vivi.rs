@@ -1,101 +1,120 @@
 // vivisix - symbolic mathematical computator
 extern crate vivi;
+use crate::vivi::vivi::eval::Env;
+use crate::vivi::vivi::session::{derivative_variable, Session, HELP};
 use crate::vivi::vivi::vivi::*;
 
 use std::env;
+use std::io::{self, BufRead, Write};
 use std::process;
-use text_io::read;
 
 #[derive(Debug)]
-struct Config {
-    query: String,
-    flags: Vec<String>,
-    none: bool,
+enum Command {
+    Repl,
+    Simplify { query: String },
+    Differentiate { query: String, variable: String },
+    // bindings are NAME=VALUE, each value can use the ones before it
+    Eval { query: String, bindings: Vec<String> },
+    Help,
+    Version,
 }
 
 const PROJECT_NAME: &str = "vivi";
 const VERSION: &str = "0.1.0";
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    let config = Config::new(&args).unwrap_or_else(|err| {
-        println!("Problem parsing arguments: {}", err);
+    let args: Vec<String> = env::args().skip(1).collect();
+    let command = Command::new(&args).unwrap_or_else(|err| {
+        eprintln!("Problem parsing arguments: {}", err);
+        eprintln!("try vivi --help");
         process::exit(1);
     });
 
-    println!("query: {}", config.query);
-    println!("flags: {:?}", config.flags);
-
-    if config.none {
-        repl();
-    }
-
-    let mut evaluator = Vivi::new(&config.query);
-
-    run(config, &mut evaluator).unwrap_or_else(|err| {
-        println!("Problem evaluating query: {}", err);
+    run(command, &Vivi::new()).unwrap_or_else(|err| {
+        eprintln!("Problem evaluating query: {}", err);
         process::exit(1);
     });
 }
 
-fn repl() {
-    println!("{} {}", PROJECT_NAME, VERSION);
-    let evaluator = Vivi::new(&"".to_string());
+fn repl() -> io::Result<()> {
+    println!("{} {}, :help for help", PROJECT_NAME, VERSION);
+    let mut session = Session::new();
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
 
     loop {
         print!("> ");
-        let line: String = read!("{}\n");
-        if line.len() == 0 {
-            process::exit(0);
+        io::stdout().flush()?;
+        let line = match lines.next() {
+            Some(line) => sanitize_query(&line?),
+            None => break,
+        };
+        match line.trim() {
+            "" => continue,
+            ":quit" | ":q" => break,
+            line => match session.run(line) {
+                Ok(reply) => println!("{}", reply),
+                Err(err) => println!("error: {}", err),
+            },
         }
-                   
-        evaluator.differentiate(&sanitize_query(&line), "x");
     }
+    println!();
+    Ok(())
 }
 
-macro_rules! vec_of_strings {
-    ($($x:expr),*) => (vec![$($x.to_string()),*]);
-}
-
-fn run(config: Config, evaluator: &mut Vivi) -> Result<(), &str> {
-    if config.flags.len() == 0 {
-        // TEST queries
-        let e1 = vec_of_strings!["x"];
-        let e2 = vec_of_strings!["2"];
-        let s1 = evaluator.make_sum(e1, e2);
-        let f1 = vec_of_strings!["x"];
-        let f2 = vec_of_strings!["5"];
-        let s2 = evaluator.make_sum(f1, f2);
-        let sum = evaluator.make_sum(s1, s2);
-        println!("{:?}", sum);
-    }
-
-    for flag in config.flags {
-        return match flag.as_str() {
-            "-dx" => Ok(evaluator.differentiate(&config.query, "x")),
-            s if s == "-h" || s == "--help" => Ok(print_help()),
-            s if s == "-V" || s == "--version" => Ok(println!("{} {}", PROJECT_NAME, VERSION)),
-            _ => Err("flag not found"),
+fn run(command: Command, evaluator: &Vivi) -> Result<(), String> {
+    match command {
+        Command::Repl => repl().map_err(|e| e.to_string())?,
+        Command::Simplify { query } => println!("{}", evaluator.simplify(&query)?),
+        Command::Differentiate { query, variable } => println!("{}", evaluator.differentiate(&query, &variable)?),
+        Command::Eval { query, bindings } => {
+            let mut env = Env::new();
+            for binding in &bindings {
+                let (name, value) = binding.split_once('=').ok_or(format!("expected NAME=VALUE, not {}", binding))?;
+                env.set(name.trim(), evaluator.evaluate(value, &env)?);
+            }
+            println!("{}", evaluator.evaluate(&query, &env)?);
         }
+        Command::Help => print_help(),
+        Command::Version => println!("{} {}", PROJECT_NAME, VERSION),
     }
-    Err("END OF RUN")
+    Ok(())
 }
 
-impl Config {
-    fn new(args: &Vec<String>) -> Result<Self, &str> {
-        if args.len() == 1 {
-            return Ok(Config { query: "".to_string(), flags: [].to_vec(), none: true })
-        } else if args.len() == 2 {
-            return Ok(Config { query: sanitize_query(&args[1]), flags: [].to_vec(), none: false })
+impl Command {
+    fn new(args: &[String]) -> Result<Self, String> {
+        let args: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+        match args.as_slice() {
+            [] | ["-i"] => Ok(Command::Repl),
+            ["-h", ..] | ["--help", ..] => Ok(Command::Help),
+            ["-V", ..] | ["--version", ..] => Ok(Command::Version),
+            ["simplify", q] => Ok(Command::Simplify { query: sanitize_query(q) }),
+            ["eval", q, bindings @ ..] => Ok(Command::Eval {
+                query: sanitize_query(q),
+                bindings: bindings.iter().map(|b| sanitize_query(b)).collect(),
+            }),
+            [op, q] if op.starts_with("d/d") => Ok(Command::Differentiate {
+                query: sanitize_query(q),
+                variable: variable(derivative_variable(&op.to_lowercase()), op)?,
+            }),
+            // the older spelling, vivi 'x * x' -dx
+            [q, flag] if flag.starts_with("-d") => Ok(Command::Differentiate {
+                query: sanitize_query(q),
+                variable: variable(derivative_variable(&format!("d/{}", &flag[1..].to_lowercase())), flag)?,
+            }),
+            ["simplify"] | ["eval", ..] => Err(format!("{} needs a query", args[0])),
+            [op] if op.starts_with("d/d") => Err(format!("{} needs a query", op)),
+            [q] if !q.starts_with('-') => Ok(Command::Simplify { query: sanitize_query(q) }),
+            [flag] => Err(format!("flag not found: {}", flag)),
+            [_, extra, ..] => Err(format!("unexpected argument: {}", extra)),
         }
-
-        let query = sanitize_query(&args[1]);
-        let flags = args[2..].iter().map(|s| s.to_string()).collect();
-
-        Ok(Config { query, flags, none: false })
     }
 }
 
+fn variable(variable: Option<&str>, op: &str) -> Result<String, String> {
+    variable.map(|v| v.to_string()).ok_or(format!("no variable to differentiate by in {}", op))
+}
+
 fn sanitize_query(query: &str) -> String {
     query.chars()
          .filter(|c| c.is_alphanumeric() || c.is_ascii_punctuation() || c.is_whitespace())
@@ -104,20 +123,31 @@ fn sanitize_query(query: &str) -> String {
 }
 
 fn print_help() {
-    println!("{}", "
+    println!("
     USAGE:
-        vivi [FLAGS] [QUERY]
-    
+        vivi [FLAGS]
+        vivi [QUERY]                        simplify
+        vivi d/dVAR [QUERY]                 differentiate with respect to VAR
+        vivi simplify [QUERY]
+        vivi eval [QUERY] [NAME=VALUE]...   evaluate
+
     FLAGS:
         -h, --help       Prints help information
+        -i               start interactive prompt, also what vivi alone does
         -V, --version    Prints version information
-    
+
     ARGS:
         <QUERY>    a mathematically-encoded string
-     
-    EXAMPLE:
-        vivi 'x * x + 22' -dx
-        'x + x'
 
-    ");
-}
\ No newline at end of file
+    EXAMPLE:
+        vivi d/dx 'x * x + 22'
+        2 * x
+        vivi d/dt 'sin(t^2)'
+        2 * t * cos(t^2)
+        vivi eval 'x^2 + y' x=3 y=4
+        13
+
+    PROMPT:
+{}
+    ", HELP);
+}
@@ -3,7 +3,482 @@ pub mod vivi;
 
 #[cfg(test)]
 mod tests {
-	use super::*;
+    use super::*;
+    use vivi::eval::Env;
+    use vivi::expr::Expr;
+    use vivi::parse::{is_identifier, parse, tokenize, Ast, Op, Token};
+    use vivi::session::{derivative_variable, Session};
+    use vivi::vivi::Vivi;
 
-    // #[test]
+    fn num(n: f64) -> Box<Ast> {
+        Box::new(Ast::Num(n))
+    }
+
+    fn var(name: &str) -> Box<Ast> {
+        Box::new(Ast::Var(name.to_string()))
+    }
+
+    fn binary(op: Op, lhs: Box<Ast>, rhs: Box<Ast>) -> Box<Ast> {
+        Box::new(Ast::Binary(op, lhs, rhs))
+    }
+
+    #[test]
+    fn tokenizes_with_positions() {
+        let tokens = tokenize("2.5*sin(x) ^ y").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                (Token::Num(2.5), 0),
+                (Token::Op('*'), 3),
+                (Token::Ident("sin".to_string()), 4),
+                (Token::LParen, 7),
+                (Token::Ident("x".to_string()), 8),
+                (Token::RParen, 9),
+                (Token::Op('^'), 11),
+                (Token::Ident("y".to_string()), 13),
+            ]
+        );
+        assert_eq!(tokenize("x $ 2").unwrap_err().position, 2);
+        assert_eq!(tokenize("1.2.3").unwrap_err().position, 0);
+
+        // exponents belong to the number, an e with no digits after it is the constant
+        let numbers = |query| tokenize(query).unwrap().into_iter().map(|(token, _)| token).collect::<Vec<_>>();
+        assert_eq!(numbers("1e3*x"), [Token::Num(1000.), Token::Op('*'), Token::Ident("x".to_string())]);
+        assert_eq!(numbers("2.5e-3 + 1E+2"), [Token::Num(0.0025), Token::Op('+'), Token::Num(100.)]);
+        assert_eq!(numbers("2e"), [Token::Num(2.), Token::Ident("e".to_string())]);
+        assert_eq!(numbers("2e-x"), [Token::Num(2.), Token::Ident("e".to_string()), Token::Op('-'), Token::Ident("x".to_string())]);
+    }
+
+    #[test]
+    fn precedence_and_associativity() {
+        // * before +, not just whatever sits at expr[1]
+        assert_eq!(
+            parse("x * x + 22").unwrap(),
+            *binary(Op::Add, binary(Op::Mul, var("x"), var("x")), num(22.))
+        );
+        assert_eq!(
+            parse("1 + 2 * 3").unwrap(),
+            *binary(Op::Add, num(1.), binary(Op::Mul, num(2.), num(3.)))
+        );
+        // - and / group to the left, ^ to the right
+        assert_eq!(
+            parse("a - b - c").unwrap(),
+            *binary(Op::Sub, binary(Op::Sub, var("a"), var("b")), var("c"))
+        );
+        assert_eq!(
+            parse("a / b / c").unwrap(),
+            *binary(Op::Div, binary(Op::Div, var("a"), var("b")), var("c"))
+        );
+        assert_eq!(
+            parse("a ^ b ^ c").unwrap(),
+            *binary(Op::Pow, var("a"), binary(Op::Pow, var("b"), var("c")))
+        );
+    }
+
+    #[test]
+    fn parentheses_and_unary_minus() {
+        assert_eq!(
+            parse("(x+1)*x").unwrap(),
+            *binary(Op::Mul, binary(Op::Add, var("x"), num(1.)), var("x"))
+        );
+        assert_eq!(parse("-x^2").unwrap(), Ast::Neg(binary(Op::Pow, var("x"), num(2.))));
+        assert_eq!(parse("(-x)^2").unwrap(), *binary(Op::Pow, Box::new(Ast::Neg(var("x"))), num(2.)));
+        assert_eq!(parse("2^-x").unwrap(), *binary(Op::Pow, num(2.), Box::new(Ast::Neg(var("x")))));
+        assert_eq!(parse("--x").unwrap(), Ast::Neg(Box::new(Ast::Neg(var("x")))));
+        assert_eq!(parse("x - -1").unwrap(), *binary(Op::Sub, var("x"), Box::new(Ast::Neg(num(1.)))));
+    }
+
+    #[test]
+    fn calls_and_implicit_products() {
+        assert_eq!(
+            parse("sin(x + 1)").unwrap(),
+            Ast::Call("sin".to_string(), vec![*binary(Op::Add, var("x"), num(1.))])
+        );
+        assert_eq!(
+            parse("max(x, 2)").unwrap(),
+            Ast::Call("max".to_string(), vec![Ast::Var("x".to_string()), Ast::Num(2.)])
+        );
+        assert_eq!(parse("2x").unwrap(), *binary(Op::Mul, num(2.), var("x")));
+        assert_eq!(
+            parse("3(x + 1)^2").unwrap(),
+            *binary(Op::Mul, num(3.), binary(Op::Pow, binary(Op::Add, var("x"), num(1.)), num(2.)))
+        );
+        assert_eq!(parse("-2x").unwrap(), *binary(Op::Mul, Box::new(Ast::Neg(num(2.))), var("x")));
+        assert_eq!(parse("1/2x").unwrap(), *binary(Op::Mul, binary(Op::Div, num(1.), num(2.)), var("x")));
+        assert_eq!(parse("x^2x").unwrap(), *binary(Op::Mul, binary(Op::Pow, var("x"), num(2.)), var("x")));
+        assert_eq!(
+            parse("(x + 1)(x - 1)").unwrap(),
+            *binary(Op::Mul, binary(Op::Add, var("x"), num(1.)), binary(Op::Sub, var("x"), num(1.)))
+        );
+        assert_eq!(
+            parse("x sin(x)").unwrap(),
+            *binary(Op::Mul, var("x"), Box::new(Ast::Call("sin".to_string(), vec![Ast::Var("x".to_string())])))
+        );
+        assert_eq!(parse("x y").unwrap(), *binary(Op::Mul, var("x"), var("y")));
+    }
+
+    #[test]
+    fn parse_errors_point_at_the_problem() {
+        let cases = [("x +", 3), ("(x + 1", 6), ("x + 1)", 5), ("x 2", 2), ("* x", 0), ("f(x,", 4), ("", 0)];
+        for (query, position) in cases.iter() {
+            let err = parse(query).unwrap_err();
+            assert_eq!(err.position, *position, "{}: {}", query, err);
+        }
+    }
+
+    #[test]
+    fn prints_minimal_brackets() {
+        let cases = [
+            ("x * x + 22", "x * x + 22"),
+            ("(x + 1) * x", "(x + 1) * x"),
+            ("((x))", "x"),
+            ("a - (b - c)", "a - (b - c)"),
+            ("(a - b) - c", "a - b - c"),
+            ("a / (b * c)", "a / (b * c)"),
+            ("(a ^ b) ^ c", "(a^b)^c"),
+            ("a ^ (b ^ c)", "a^b^c"),
+            ("-(x + 1)", "-(x + 1)"),
+            ("-x^2", "-x^2"),
+            ("(-x)^2", "(-x)^2"),
+            ("sin(x,y)", "sin(x, y)"),
+        ];
+        for (query, printed) in cases.iter() {
+            let ast = parse(query).unwrap();
+            assert_eq!(ast.to_string(), *printed);
+            // and what gets printed reads back the same
+            assert_eq!(parse(printed).unwrap(), ast);
+        }
+    }
+
+    #[test]
+    fn differentiates_sums_and_products() {
+        let vivi = Vivi::new();
+        let d = |query: &str| vivi.differentiate(query, "x").unwrap().to_string();
+        assert_eq!(d("x * x + 22"), "2 * x");
+        assert_eq!(d("(x + 1) * x"), "2 * x + 1");
+        assert_eq!(d("3 * x"), "3");
+        assert_eq!(d("y * x + y"), "y");
+        assert!(vivi.differentiate("x +", "x").is_err());
+        assert_eq!(d("x - 3 * x"), "-2");
+        assert_eq!(d("-(y * x)"), "-y");
+        assert!(vivi.differentiate("f(x)", "x").is_err());
+    }
+
+    #[test]
+    fn differentiates_powers_and_quotients() {
+        let vivi = Vivi::new();
+        let d = |query: &str| vivi.differentiate(query, "x").unwrap().to_string();
+        assert_eq!(d("x^2"), "2 * x");
+        assert_eq!(d("x^3 - x"), "3 * x^2 - 1");
+        assert_eq!(d("x^0.5"), "0.5 / x^0.5");
+        assert_eq!(d("1 / x"), "-1 / x^2");
+        assert_eq!(d("x / (x + 1)"), "1 / (x + 1)^2");
+        assert_eq!(d("x / 2"), "0.5");
+        assert_eq!(d("2^x"), "2^x * ln(2)");
+        assert_eq!(d("x^x"), "x^x * (ln(x) + 1)");
+        assert_eq!(d("y^2"), "0");
+        assert_eq!(d("(x^2 + 1)^3"), "6 * x * (x^2 + 1)^2");
+    }
+
+    #[test]
+    fn differentiates_functions_by_the_chain_rule() {
+        let vivi = Vivi::new();
+        let d = |query: &str| vivi.differentiate(query, "x").unwrap().to_string();
+        assert_eq!(d("sin(x)"), "cos(x)");
+        assert_eq!(d("cos(x)"), "-sin(x)");
+        assert_eq!(d("tan(x)"), "tan(x)^2 + 1");
+        assert_eq!(d("exp(x)"), "exp(x)");
+        assert_eq!(d("ln(x)"), "1 / x");
+        assert_eq!(d("sqrt(x)"), "0.5 / sqrt(x)");
+        assert_eq!(d("sin(x^2)"), "2 * x * cos(x^2)");
+        assert_eq!(d("cos(x^2)"), "-2 * x * sin(x^2)");
+        assert_eq!(d("exp(2x)"), "2 * exp(2 * x)");
+        assert_eq!(d("ln(x^2 + 1)"), "2 * x / (x^2 + 1)");
+        assert_eq!(d("sin(y)"), "0");
+        // in another variable
+        assert_eq!(vivi.differentiate("x * sin(t)", "t").unwrap().to_string(), "x * cos(t)");
+    }
+
+    #[test]
+    fn differentiation_errors() {
+        let vivi = Vivi::new();
+        let err = |query: &str| vivi.differentiate(query, "x").unwrap_err();
+        assert!(err("f(x)").contains("unknown function f"));
+        assert!(err("sin(x, 2)").contains("one argument"));
+        assert!(err("sin()").contains("one argument"));
+        assert!(err("x +").contains("end of query"));
+    }
+
+    fn simplified(query: &str) -> String {
+        Vivi::new().simplify(query).unwrap().to_string()
+    }
+
+    #[test]
+    fn collects_like_terms() {
+        assert_eq!(simplified("x + x"), "2 * x");
+        assert_eq!(simplified("x + 2 + (x + 5)"), "2 * x + 7");
+        assert_eq!(simplified("3 * x - x * 3"), "0");
+        assert_eq!(simplified("x * y + y * x"), "2 * x * y");
+        assert_eq!(simplified("-(x - y)"), "-x + y");
+        assert_eq!(simplified("2 * sin(x) - sin(x) + 1 - 1"), "sin(x)");
+        assert_eq!(simplified("0.5 * x + x / 2"), "x");
+    }
+
+    #[test]
+    fn merges_powers() {
+        assert_eq!(simplified("x * x * x"), "x^3");
+        assert_eq!(simplified("x^2 * x^-2"), "1");
+        assert_eq!(simplified("x^a * x^b"), "x^(a + b)");
+        assert_eq!(simplified("(x^2)^3"), "x^6");
+        assert_eq!(simplified("(2 * x)^2"), "4 * x^2");
+        assert_eq!(simplified("x^2 * y^3 / (x * y)"), "x * y^2");
+        assert_eq!(simplified("2 / (4 * x)"), "0.5 / x");
+        assert_eq!(simplified("-x / y^2"), "-x / y^2");
+        assert_eq!(simplified("(x + 1) * (x + 1)"), "(x + 1)^2");
+        // nothing to fold into
+        assert_eq!(simplified("1 / 0"), "1 / 0");
+    }
+
+    #[test]
+    fn orders_canonically() {
+        assert_eq!(simplified("1 + x + x^2"), "x^2 + x + 1");
+        assert_eq!(simplified("y * 3 * x"), "3 * x * y");
+        assert_eq!(simplified("sin(x) * x"), "x * sin(x)");
+        assert_eq!(simplified("1 / x + x + 2"), "x + 2 + 1 / x");
+        assert_eq!(simplified("b + a"), simplified("a + b"));
+        assert_eq!(simplified("(y + x) * z"), simplified("z * (x + y)"));
+    }
+
+    #[test]
+    fn folds_constants() {
+        assert_eq!(simplified("2 * 3 + 4^0.5"), "8");
+        assert_eq!(simplified("sin(0) + cos(0) + exp(0) + ln(1)"), "2");
+        assert_eq!(simplified("sqrt(16) * x"), "4 * x");
+        assert_eq!(simplified("sqrt(2)"), "sqrt(2)");
+        assert_eq!(simplified("ln(exp(x + 1))"), "x + 1");
+    }
+
+    #[test]
+    fn simplified_forms_are_stable() {
+        let queries = [
+            "x^x * (ln(x) + x / x)",
+            "(x + 1 - x) / (x + 1)^2",
+            "-(2 * x * sin(x^2)) + 3 * (x^2 + 1)^2 * (2 * x)",
+            "-x^-2 + 0.5 * x^-0.5",
+            "(x + 1) / (x - 1) - 2 / (x - 1)",
+            "a * b^-1 * c^-2 - y",
+        ];
+        let vivi = Vivi::new();
+        for query in queries.iter() {
+            let once = vivi.simplify(query).unwrap();
+            assert_eq!(once.simplify(), once, "{}", query);
+            // printing and reading back lands on the same form
+            assert_eq!(vivi.simplify(&once.to_string()).unwrap(), once, "{}", once);
+        }
+    }
+
+    #[test]
+    fn constructors_simplify() {
+        let x = || Expr::make_variable("x");
+        assert_eq!(Expr::make_sum(Expr::make_number(2.), Expr::make_number(3.)), Expr::make_number(5.));
+        assert_eq!(Expr::make_sum(Expr::make_number(0.), x()), x());
+        assert_eq!(Expr::make_product(x(), Expr::make_number(1.)), x());
+        assert_eq!(Expr::make_product(Expr::make_number(0.), x()), Expr::make_number(0.));
+        assert_eq!(Expr::make_product(Expr::make_number(-1.), x()), Expr::make_negation(x()));
+        assert_eq!(Expr::make_power(x(), Expr::make_number(0.)), Expr::make_number(1.));
+        assert_eq!(Expr::make_power(x(), Expr::make_number(1.)), x());
+        assert_eq!(Expr::make_power(Expr::make_number(2.), Expr::make_number(10.)), Expr::make_number(1024.));
+        assert_eq!(Expr::make_negation(Expr::make_negation(x())), x());
+        assert_eq!(Expr::make_difference(x(), Expr::make_number(2.)), Expr::make_sum(x(), Expr::make_number(-2.)));
+        assert_eq!(Expr::make_quotient(Expr::make_number(1.), Expr::make_number(4.)), Expr::make_number(0.25));
+        // 1 / 0 stays a division rather than folding to infinity
+        assert_eq!(Expr::make_quotient(Expr::make_number(1.), Expr::make_number(0.)).to_string(), "1 / 0");
+        // and zero doesn't swallow it, 0 / 0 isn't 0
+        assert_eq!(Expr::make_quotient(Expr::make_number(0.), Expr::make_number(0.)).to_string(), "0 / 0");
+        assert_eq!(simplified("0 / 0"), "0 / 0");
+        assert_eq!(simplified("3 * (0 / 0) + 1"), "1 + 0 / 0");
+        assert_eq!(simplified("0 * x"), "0");
+    }
+
+    #[test]
+    fn expressions_from_the_parser() {
+        let read = |query: &str| Expr::from(parse(query).unwrap());
+        assert_eq!(read("x * 1 + 0"), Expr::make_variable("x"));
+        assert_eq!(read("(x + 2) + (x + 5)").to_string(), "x + 2 + (x + 5)");
+        assert_eq!(
+            read("a - b"),
+            Expr::Add(Box::new(Expr::make_variable("a")), Box::new(Expr::make_negation(Expr::make_variable("b"))))
+        );
+        assert_eq!(read("a / b"), Expr::make_product(Expr::make_variable("a"), Expr::make_power(Expr::make_variable("b"), Expr::make_number(-1.))));
+        assert_eq!(read("2^3 * x"), Expr::make_product(Expr::make_number(8.), Expr::make_variable("x")));
+    }
+
+    #[test]
+    fn prints_expressions() {
+        let cases = [
+            ("a - b", "a - b"),
+            ("a - (b + c)", "a - (b + c)"),
+            ("x - 2", "x - 2"),
+            ("a / b", "a / b"),
+            ("a / (b * c)", "a / (b * c)"),
+            ("a * (b / c)", "a * (b / c)"),
+            ("-2 * x", "-2 * x"),
+            ("x^-2", "x^(-2)"),
+            ("(x + 1)^2", "(x + 1)^2"),
+            ("-(a * b)", "-(a * b)"),
+            ("sin(x - 1)", "sin(x - 1)"),
+        ];
+        for (query, printed) in cases.iter() {
+            let expr = Expr::from(parse(query).unwrap());
+            assert_eq!(expr.to_string(), *printed);
+            assert_eq!(Expr::from(parse(printed).unwrap()), expr);
+        }
+    }
+
+    fn env(bindings: &[(&str, f64)]) -> Env {
+        let mut env = Env::new();
+        for (name, value) in bindings.iter() {
+            env.set(name, *value);
+        }
+        env
+    }
+
+    #[test]
+    fn evaluates_with_bindings() {
+        let vivi = Vivi::new();
+        let eval = |query: &str, env: &Env| vivi.evaluate(query, env).unwrap();
+        assert_eq!(eval("x^2 + y", &env(&[("x", 3.), ("y", 4.)])), 13.);
+        assert_eq!(eval("-x^2", &env(&[("x", 3.)])), -9.);
+        assert_eq!(eval("2^3^2", &Env::new()), 512.);
+        assert_eq!(eval("(1 - 2) - 3", &Env::new()), -4.);
+        assert_eq!(eval("sqrt(x) / 2", &env(&[("x", 16.)])), 2.);
+        assert!((eval("sin(pi / 6) + ln(e)", &Env::new()) - 1.5).abs() < 1e-12);
+        // bindings shadow the constants
+        assert_eq!(eval("e", &env(&[("e", 2.)])), 2.);
+        assert!(eval("ln(x)", &env(&[("x", -1.)])).is_nan());
+        // evaluated as written, not as simplified
+        assert!(eval("0 / 0", &Env::new()).is_nan());
+        assert!(eval("x / x", &env(&[("x", 0.)])).is_nan());
+        assert!(eval("0 * x", &env(&[("x", f64::INFINITY)])).is_nan());
+        assert_eq!(Expr::verbatim(parse("x - 0").unwrap()).to_string(), "x - 0");
+    }
+
+    #[test]
+    fn evaluation_errors() {
+        let vivi = Vivi::new();
+        let err = |query: &str| vivi.evaluate(query, &env(&[("x", 1.)])).unwrap_err();
+        assert_eq!(err("x + y"), "y is not bound");
+        assert_eq!(err("f(x)"), "unknown function f");
+        assert_eq!(err("sin(x, x)"), "sin takes one argument, not 2");
+        assert!(Expr::make_variable("y").compile(&["x"]).is_err());
+        assert!(Expr::make_call("f", vec![]).compile(&["x"]).is_err());
+    }
+
+    #[test]
+    fn compiled_matches_eval() {
+        let vivi = Vivi::new();
+        let queries = ["x^2 + y", "x^-3 * y", "sin(x) * exp(-y) / (1 + x^2)", "x^y - pi", "sqrt(x + y)^0.5"];
+        for query in queries.iter() {
+            let expr = vivi.read(query).unwrap();
+            let compiled = expr.compile(&["x", "y"]).unwrap();
+            for &(x, y) in [(0.5, 2.), (3., -1.), (1.25, 0.)].iter() {
+                let expected = expr.eval(&env(&[("x", x), ("y", y)])).unwrap();
+                assert!((compiled(&[x, y]) - expected).abs() <= 1e-12 * expected.abs().max(1.), "{}", query);
+            }
+        }
+    }
+
+    #[test]
+    fn derivatives_match_finite_differences() {
+        let queries = [
+            "x * x + 22",
+            "3 * x^4 - 2 * x + 7",
+            "x^0.5 + x^-2",
+            "1 / x",
+            "x / (x + 1)",
+            "(x + 1) / (x^2 - 5)",
+            "2^x",
+            "x^x",
+            "(x^2 + 1)^3",
+            "sin(x) * cos(x)",
+            "tan(x^2)",
+            "exp(2 * x) * ln(x)",
+            "sqrt(x^2 + 1)",
+            "ln(sin(x) + 2)",
+            "-(x - 3)^2 / exp(x)",
+            "x * e^x",
+            "sin(cos(tan(x)))",
+        ];
+        let vivi = Vivi::new();
+        let h = 1e-6;
+        for query in queries.iter() {
+            let f = vivi.read(query).unwrap().compile(&["x"]).unwrap();
+            let derived = vivi.differentiate(query, "x").unwrap();
+            let df = derived.compile(&["x"]).unwrap();
+            for &x in [0.3, 0.7, 1.3, 2.1].iter() {
+                let numeric = (f(&[x + h]) - f(&[x - h])) / (2. * h);
+                let exact = df(&[x]);
+                assert!(
+                    (numeric - exact).abs() <= 1e-5 * exact.abs().max(1.),
+                    "d/dx {} = {} at {}: {} vs {}",
+                    query,
+                    derived,
+                    x,
+                    exact,
+                    numeric
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn session_binds_variables() {
+        let mut session = Session::new();
+        assert_eq!(session.run("x = 3").unwrap(), "x = 3");
+        assert_eq!(session.run("y = x + 1").unwrap(), "y = 4");
+        assert_eq!(session.run("x^2 + y").unwrap(), "x^2 + y = 13");
+        assert_eq!(session.run("eval x * y").unwrap(), "12");
+        // what isn't bound stays symbolic
+        assert_eq!(session.run("z + z").unwrap(), "2 * z");
+        assert_eq!(session.run("2 + 2").unwrap(), "4");
+        assert_eq!(session.run("x = x * 2").unwrap(), "x = 6");
+        assert_eq!(session.env().vars(), vec![("x", 6.), ("y", 4.)]);
+        assert_eq!(session.run(":vars").unwrap(), "x = 6\ny = 4");
+        assert!(session.run("2 = x").is_err());
+        assert!(session.run("w = z").is_err());
+    }
+
+    #[test]
+    fn session_operations() {
+        let mut session = Session::new();
+        assert_eq!(session.run("d/dx x^2 * y").unwrap(), "2 * x * y");
+        assert_eq!(session.run("d/dy x^2 * y").unwrap(), "x^2");
+        assert_eq!(session.run("d/dtheta sin(theta)").unwrap(), "cos(theta)");
+        assert_eq!(session.run("simplify x + x").unwrap(), "2 * x");
+        assert!(session.run("eval z").is_err());
+        assert!(session.run(":nope").is_err());
+        assert!(session.run(":help").unwrap().contains("d/dVAR"));
+        assert!(session.run(":help").unwrap().lines().all(|line| line.starts_with("    ")));
+        assert_eq!(derivative_variable("d/dx"), Some("x"));
+        assert_eq!(derivative_variable("d/d"), None);
+        assert_eq!(derivative_variable("d/d2"), None);
+        assert!(is_identifier("x_1") && !is_identifier("1x") && !is_identifier(""));
+    }
+
+    #[test]
+    fn session_history() {
+        let mut session = Session::new();
+        session.run("a = 2").unwrap();
+        session.run("a^2").unwrap();
+        assert_eq!(session.run("!!").unwrap(), "a^2 = 4");
+        assert_eq!(session.run("!1").unwrap(), "a = 2");
+        // commands aren't kept, recalled lines are
+        session.run(":vars").unwrap();
+        assert_eq!(session.history(), &["a = 2", "a^2", "a^2", "a = 2"]);
+        assert_eq!(session.run(":history").unwrap().lines().count(), 4);
+        assert!(session.run("!9").is_err());
+        assert!(session.run("!0").is_err());
+        assert!(session.run("!x").is_err());
+        assert!(Session::new().run("!!").is_err());
+    }
 }
@@ -0,0 +1,90 @@
+// symbolic differentiation over expression trees
+// EX: (x^2 + 1)^3 -> 3 * (x^2 + 1)^2 * (2 * x)
+use crate::vivi::expr::Expr;
+
+impl Expr {
+    // the derivative with respect to var, or what couldn't be differentiated
+    pub fn derivative(&self, var: &str) -> Result<Expr, String> {
+        match self {
+            Expr::Num(_) => Ok(Expr::make_number(0.)),
+            Expr::Var(name) if name == var => Ok(Expr::make_number(1.)),
+            Expr::Var(_) => Ok(Expr::make_number(0.)),
+            Expr::Add(augend, addend) => Ok(Expr::make_sum(augend.derivative(var)?, addend.derivative(var)?)),
+            Expr::Neg(e) => Ok(Expr::make_negation(e.derivative(var)?)),
+            Expr::Mul(u, v) => match &**v {
+                // u / v -> (u'v - uv') / v^2
+                Expr::Pow(v, exponent) if **exponent == Expr::make_number(-1.) && v.contains(var) => {
+                    Ok(Expr::make_quotient(
+                        Expr::make_difference(
+                            Expr::make_product(u.derivative(var)?, (**v).clone()),
+                            Expr::make_product((**u).clone(), v.derivative(var)?),
+                        ),
+                        Expr::make_power((**v).clone(), Expr::make_number(2.)),
+                    ))
+                }
+                // uv -> uv' + u'v
+                _ => Ok(Expr::make_sum(
+                    Expr::make_product((**u).clone(), v.derivative(var)?),
+                    Expr::make_product(u.derivative(var)?, (**v).clone()),
+                )),
+            },
+            Expr::Pow(base, exponent) => power(self, base, exponent, var),
+            Expr::Call(name, args) => match args.as_slice() {
+                [u] => Ok(Expr::make_product(u.derivative(var)?, outer(name, u)?)),
+                _ => Err(format!("INVALID DIFFERENTIATION: {} takes one argument, not {}", name, args.len())),
+            },
+        }
+    }
+}
+
+fn power(whole: &Expr, u: &Expr, v: &Expr, var: &str) -> Result<Expr, String> {
+    if !v.contains(var) {
+        // u^n -> n * u^(n - 1) * u'
+        Ok(Expr::make_product(
+            Expr::make_product(
+                v.clone(),
+                Expr::make_power(u.clone(), Expr::make_difference(v.clone(), Expr::make_number(1.))),
+            ),
+            u.derivative(var)?,
+        ))
+    } else if !u.contains(var) {
+        // a^v -> a^v * ln(a) * v'
+        Ok(Expr::make_product(
+            Expr::make_product(whole.clone(), ln(u)),
+            v.derivative(var)?,
+        ))
+    } else {
+        // u^v -> u^v * (v' * ln(u) + v * u' / u)
+        Ok(Expr::make_product(
+            whole.clone(),
+            Expr::make_sum(
+                Expr::make_product(v.derivative(var)?, ln(u)),
+                Expr::make_quotient(Expr::make_product(v.clone(), u.derivative(var)?), u.clone()),
+            ),
+        ))
+    }
+}
+
+fn ln(u: &Expr) -> Expr {
+    Expr::make_call("ln", vec![u.clone()])
+}
+
+// f'(u) for the functions we know, to be multiplied by u' for the chain rule
+fn outer(name: &str, u: &Expr) -> Result<Expr, String> {
+    let call = |f: &str| Expr::make_call(f, vec![u.clone()]);
+    match name {
+        "sin" => Ok(call("cos")),
+        "cos" => Ok(Expr::make_negation(call("sin"))),
+        "tan" => Ok(Expr::make_sum(
+            Expr::make_number(1.),
+            Expr::make_power(call("tan"), Expr::make_number(2.)),
+        )),
+        "exp" => Ok(call("exp")),
+        "ln" => Ok(Expr::make_quotient(Expr::make_number(1.), u.clone())),
+        "sqrt" => Ok(Expr::make_quotient(
+            Expr::make_number(1.),
+            Expr::make_product(Expr::make_number(2.), call("sqrt")),
+        )),
+        _ => Err(format!("INVALID DIFFERENTIATION: unknown function {}", name)),
+    }
+}
@@ -0,0 +1,129 @@
+// numeric evaluation, by walking the tree or through a compiled closure
+// EX: x^2 + y with x = 3, y = 4 -> 13
+use crate::vivi::expr::Expr;
+use std::collections::HashMap;
+use std::f64::consts;
+
+// variable bindings, with pi and e there unless rebound
+#[derive(Debug, Clone, Default)]
+pub struct Env {
+    vars: HashMap<String, f64>,
+}
+
+impl Env {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, name: &str, value: f64) {
+        self.vars.insert(name.to_string(), value);
+    }
+
+    pub fn get(&self, name: &str) -> Option<f64> {
+        self.vars.get(name).copied().or_else(|| constant(name))
+    }
+
+    // what's been bound, sorted by name
+    pub fn vars(&self) -> Vec<(&str, f64)> {
+        let mut vars: Vec<(&str, f64)> = self.vars.iter().map(|(name, value)| (name.as_str(), *value)).collect();
+        vars.sort_by_key(|&(name, _)| name);
+        vars
+    }
+}
+
+fn constant(name: &str) -> Option<f64> {
+    match name {
+        "pi" => Some(consts::PI),
+        "e" => Some(consts::E),
+        _ => None,
+    }
+}
+
+type Function = fn(f64) -> f64;
+
+// the functions we know how to evaluate
+fn function(name: &str) -> Option<Function> {
+    match name {
+        "sin" => Some(f64::sin),
+        "cos" => Some(f64::cos),
+        "tan" => Some(f64::tan),
+        "exp" => Some(f64::exp),
+        "ln" => Some(f64::ln),
+        "sqrt" => Some(f64::sqrt),
+        _ => None,
+    }
+}
+
+fn unary<'a>(name: &str, args: &'a [Expr]) -> Result<(Function, &'a Expr), String> {
+    let f = function(name).ok_or(format!("unknown function {}", name))?;
+    match args {
+        [arg] => Ok((f, arg)),
+        _ => Err(format!("{} takes one argument, not {}", name, args.len())),
+    }
+}
+
+// takes the values of the variables it was compiled for, in that order
+pub type Compiled = Box<dyn Fn(&[f64]) -> f64>;
+
+impl Expr {
+    pub fn eval(&self, env: &Env) -> Result<f64, String> {
+        match self {
+            Expr::Num(n) => Ok(*n),
+            Expr::Var(name) => env.get(name).ok_or(format!("{} is not bound", name)),
+            Expr::Add(a1, a2) => Ok(a1.eval(env)? + a2.eval(env)?),
+            Expr::Mul(m1, m2) => Ok(m1.eval(env)? * m2.eval(env)?),
+            Expr::Pow(b, e) => Ok(b.eval(env)?.powf(e.eval(env)?)),
+            Expr::Neg(e) => Ok(-e.eval(env)?),
+            Expr::Call(name, args) => {
+                let (f, arg) = unary(name, args)?;
+                Ok(f(arg.eval(env)?))
+            }
+        }
+    }
+
+    // a closure over the given variables, with every name and function resolved up front
+    // EX: (x + y).compile(&["x", "y"])?(&[3., 4.]) -> 7
+    pub fn compile(&self, vars: &[&str]) -> Result<Compiled, String> {
+        Ok(match self {
+            Expr::Num(n) => {
+                let n = *n;
+                Box::new(move |_| n)
+            }
+            Expr::Var(name) => match vars.iter().position(|var| var == name) {
+                Some(i) => Box::new(move |values| values[i]),
+                None => {
+                    let n = constant(name).ok_or(format!("{} is not bound", name))?;
+                    Box::new(move |_| n)
+                }
+            },
+            Expr::Add(a1, a2) => {
+                let (a1, a2) = (a1.compile(vars)?, a2.compile(vars)?);
+                Box::new(move |values| a1(values) + a2(values))
+            }
+            Expr::Mul(m1, m2) => {
+                let (m1, m2) = (m1.compile(vars)?, m2.compile(vars)?);
+                Box::new(move |values| m1(values) * m2(values))
+            }
+            Expr::Pow(b, e) => match **e {
+                // squares and other whole powers are much cheaper with powi
+                Expr::Num(n) if n.fract() == 0. && n.abs() <= i32::MAX as f64 => {
+                    let (b, n) = (b.compile(vars)?, n as i32);
+                    Box::new(move |values| b(values).powi(n))
+                }
+                _ => {
+                    let (b, e) = (b.compile(vars)?, e.compile(vars)?);
+                    Box::new(move |values| b(values).powf(e(values)))
+                }
+            },
+            Expr::Neg(e) => {
+                let e = e.compile(vars)?;
+                Box::new(move |values| -e(values))
+            }
+            Expr::Call(name, args) => {
+                let (f, arg) = unary(name, args)?;
+                let arg = arg.compile(vars)?;
+                Box::new(move |values| f(arg(values)))
+            }
+        })
+    }
+}
@@ -0,0 +1,231 @@
+// expression trees, built through constructors that simplify as they go
+// a - b is stored as a + -b, and a / b as a * b^-1
+use crate::vivi::parse::{Ast, Op};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Num(f64),
+    Var(String),
+    Add(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+    Pow(Box<Expr>, Box<Expr>),
+    Neg(Box<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+impl Expr {
+    pub fn make_number(n: f64) -> Expr {
+        Expr::Num(n)
+    }
+
+    pub fn make_variable(name: &str) -> Expr {
+        Expr::Var(name.to_string())
+    }
+
+    // a1 + a2, folding numbers and dropping zeros
+    pub fn make_sum(a1: Expr, a2: Expr) -> Expr {
+        match (a1, a2) {
+            (Expr::Num(n1), Expr::Num(n2)) => Expr::Num(n1 + n2),
+            (Expr::Num(n), a) | (a, Expr::Num(n)) if n == 0. => a,
+            (a1, a2) => Expr::Add(Box::new(a1), Box::new(a2)),
+        }
+    }
+
+    pub fn make_difference(a1: Expr, a2: Expr) -> Expr {
+        Expr::make_sum(a1, Expr::make_negation(a2))
+    }
+
+    // m1 * m2, folding numbers, dropping ones and zeroing on zero
+    // unless the other side is a power like 0^-1, since 0 / 0 isn't 0
+    pub fn make_product(m1: Expr, m2: Expr) -> Expr {
+        match (m1, m2) {
+            (Expr::Num(n1), Expr::Num(n2)) => Expr::Num(n1 * n2),
+            (Expr::Num(n), m) | (m, Expr::Num(n)) if n == 1. => m,
+            (Expr::Num(n), m) | (m, Expr::Num(n)) if n == 0. && !m.is_singular() => Expr::Num(0.),
+            (Expr::Num(n), m) | (m, Expr::Num(n)) if n == -1. => Expr::make_negation(m),
+            // the sign goes out front, -a * b is -(a * b)
+            (Expr::Neg(m1), m2) => Expr::make_negation(Expr::make_product(*m1, m2)),
+            (m1, Expr::Neg(m2)) => Expr::make_negation(Expr::make_product(m1, *m2)),
+            (m1, m2) => Expr::Mul(Box::new(m1), Box::new(m2)),
+        }
+    }
+
+    pub fn make_quotient(m1: Expr, m2: Expr) -> Expr {
+        Expr::make_product(m1, Expr::make_power(m2, Expr::Num(-1.)))
+    }
+
+    // base^exponent, folding numbers unless that would make an infinity or NaN
+    pub fn make_power(base: Expr, exponent: Expr) -> Expr {
+        match (base, exponent) {
+            (_, Expr::Num(0.)) => Expr::Num(1.),
+            (b, Expr::Num(1.)) => b,
+            (Expr::Num(1.), _) => Expr::Num(1.),
+            (Expr::Num(b), Expr::Num(e)) if b.powf(e).is_finite() => Expr::Num(b.powf(e)),
+            (b, e) => Expr::Pow(Box::new(b), Box::new(e)),
+        }
+    }
+
+    pub fn make_negation(e: Expr) -> Expr {
+        match e {
+            Expr::Num(n) => Expr::Num(-n),
+            Expr::Neg(inner) => *inner,
+            e => Expr::Neg(Box::new(e)),
+        }
+    }
+
+    pub fn make_call(name: &str, args: Vec<Expr>) -> Expr {
+        Expr::Call(name.to_string(), args)
+    }
+
+    // whether var appears anywhere in the expression
+    pub fn contains(&self, var: &str) -> bool {
+        match self {
+            Expr::Num(_) => false,
+            Expr::Var(name) => name == var,
+            Expr::Add(e1, e2) | Expr::Mul(e1, e2) | Expr::Pow(e1, e2) => e1.contains(var) || e2.contains(var),
+            Expr::Neg(e) => e.contains(var),
+            Expr::Call(_, args) => args.iter().any(|arg| arg.contains(var)),
+        }
+    }
+
+    // a numeric power make_power left alone because it has no finite value, like 0^-1
+    pub fn is_singular(&self) -> bool {
+        match self {
+            Expr::Pow(b, e) => singular(b, e),
+            _ => false,
+        }
+    }
+
+    fn is_reciprocal(&self) -> bool {
+        matches!(self, Expr::Pow(_, e) if **e == Expr::Num(-1.))
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Add(_, _) => 1,
+            Expr::Mul(_, _) => 2,
+            // b^-1 prints as 1 / b
+            e if e.is_reciprocal() => 2,
+            Expr::Neg(_) => 3,
+            Expr::Num(n) if *n < 0. => 3,
+            Expr::Pow(_, _) => 4,
+            _ => 5,
+        }
+    }
+}
+
+pub(crate) fn singular(base: &Expr, exponent: &Expr) -> bool {
+    matches!((base, exponent), (Expr::Num(b), Expr::Num(e)) if !b.powf(*e).is_finite())
+}
+
+impl Expr {
+    // the tree just as the query was written, with nothing folded or dropped
+    // what evaluation goes through, so 0 * x or x / x give what they would with x put in
+    pub fn verbatim(ast: Ast) -> Expr {
+        match ast {
+            Ast::Num(n) => Expr::Num(n),
+            Ast::Var(name) => Expr::Var(name),
+            Ast::Neg(e) => Expr::Neg(Box::new(Expr::verbatim(*e))),
+            Ast::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (Box::new(Expr::verbatim(*lhs)), Box::new(Expr::verbatim(*rhs)));
+                match op {
+                    Op::Add => Expr::Add(lhs, rhs),
+                    Op::Sub => Expr::Add(lhs, Box::new(Expr::Neg(rhs))),
+                    Op::Mul => Expr::Mul(lhs, rhs),
+                    Op::Div => Expr::Mul(lhs, Box::new(Expr::Pow(rhs, Box::new(Expr::Num(-1.))))),
+                    Op::Pow => Expr::Pow(lhs, rhs),
+                }
+            }
+            Ast::Call(name, args) => Expr::Call(name, args.into_iter().map(Expr::verbatim).collect()),
+        }
+    }
+}
+
+impl From<Ast> for Expr {
+    fn from(ast: Ast) -> Expr {
+        match ast {
+            Ast::Num(n) => Expr::Num(n),
+            Ast::Var(name) => Expr::Var(name),
+            Ast::Neg(e) => Expr::make_negation(Expr::from(*e)),
+            Ast::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (Expr::from(*lhs), Expr::from(*rhs));
+                match op {
+                    Op::Add => Expr::make_sum(lhs, rhs),
+                    Op::Sub => Expr::make_difference(lhs, rhs),
+                    Op::Mul => Expr::make_product(lhs, rhs),
+                    Op::Div => Expr::make_quotient(lhs, rhs),
+                    Op::Pow => Expr::make_power(lhs, rhs),
+                }
+            }
+            Ast::Call(name, args) => Expr::Call(name, args.into_iter().map(Expr::from).collect()),
+        }
+    }
+}
+
+fn bracket(f: &mut fmt::Formatter, e: &Expr, needed: bool) -> fmt::Result {
+    match needed {
+        true => write!(f, "({})", e),
+        false => write!(f, "{}", e),
+    }
+}
+
+// a + -b prints as a - b and a * b^-1 as a / b, with only the brackets the precedence needs
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Num(n) => write!(f, "{}", n),
+            Expr::Var(name) => write!(f, "{}", name),
+            Expr::Add(a1, a2) => {
+                write!(f, "{}", a1)?;
+                match &**a2 {
+                    Expr::Neg(e) => {
+                        write!(f, " - ")?;
+                        bracket(f, e, e.precedence() <= 1)
+                    }
+                    Expr::Num(n) if *n < 0. => write!(f, " - {}", -n),
+                    e => {
+                        write!(f, " + ")?;
+                        bracket(f, e, e.precedence() <= 1)
+                    }
+                }
+            }
+            Expr::Mul(m1, m2) => {
+                bracket(f, m1, m1.precedence() < 2)?;
+                match &**m2 {
+                    Expr::Pow(b, _) if m2.is_reciprocal() => {
+                        write!(f, " / ")?;
+                        bracket(f, b, b.precedence() <= 2)
+                    }
+                    e => {
+                        write!(f, " * ")?;
+                        bracket(f, e, e.precedence() <= 2)
+                    }
+                }
+            }
+            Expr::Pow(b, _) if self.is_reciprocal() => {
+                write!(f, "1 / ")?;
+                bracket(f, b, b.precedence() <= 2)
+            }
+            Expr::Pow(b, e) => {
+                bracket(f, b, b.precedence() <= 4)?;
+                write!(f, "^")?;
+                bracket(f, e, e.precedence() < 4)
+            }
+            Expr::Neg(e) => {
+                write!(f, "-")?;
+                bracket(f, e, e.precedence() < 4)
+            }
+            Expr::Call(name, args) => {
+                write!(f, "{}(", name)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
@@ -1 +1,8 @@
+pub mod deriv;
+pub mod eval;
+pub mod expr;
+pub mod parse;
+pub mod session;
+pub mod simplify;
+#[allow(clippy::module_inception)]
 pub mod vivi;
\ No newline at end of file
@@ -0,0 +1,322 @@
+// tokenizer and Pratt parser, from a query string to a syntax tree
+// EX: parse("(x + 1) * x") -> Binary(Mul, Binary(Add, Var(x), Num(1)), Var(x))
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Num(f64),
+    Ident(String),
+    // + - * / ^
+    Op(char),
+    LParen,
+    RParen,
+    Comma,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ast {
+    Num(f64),
+    Var(String),
+    Neg(Box<Ast>),
+    Binary(Op, Box<Ast>, Box<Ast>),
+    Call(String, Vec<Ast>),
+}
+
+// what went wrong, and where in the query (in chars)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub message: String,
+    pub position: usize,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at {}", self.message, self.position)
+    }
+}
+
+impl Error for ParseError {}
+
+fn error<T>(message: &str, position: usize) -> Result<T, ParseError> {
+    Err(ParseError {
+        message: message.to_string(),
+        position,
+    })
+}
+
+// a name the tokenizer reads as one variable or function
+pub fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '_' => chars.all(|c| c.is_alphanumeric() || c == '_'),
+        _ => false,
+    }
+}
+
+// every token of the query, with the position it starts at
+pub fn tokenize(query: &str) -> Result<Vec<(Token, usize)>, ParseError> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let ch = chars[i];
+        let start = i;
+        let token = match ch {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                // an exponent, as in 1e3 or 2.5e-3, but 2e alone is 2 times e
+                if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                    let digits = match chars.get(i + 1) {
+                        Some('+') | Some('-') => i + 2,
+                        _ => i + 1,
+                    };
+                    if chars.get(digits).is_some_and(|c| c.is_ascii_digit()) {
+                        i = digits;
+                        while i < chars.len() && chars[i].is_ascii_digit() {
+                            i += 1;
+                        }
+                    }
+                }
+                let number: String = chars[start..i].iter().collect();
+                match number.parse() {
+                    Ok(n) => Token::Num(n),
+                    Err(_) => return error(&format!("bad number '{}'", number), start),
+                }
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                Token::Ident(chars[start..i].iter().collect())
+            }
+            '+' | '-' | '*' | '/' | '^' => {
+                i += 1;
+                Token::Op(ch)
+            }
+            '(' | ')' | ',' => {
+                i += 1;
+                match ch {
+                    '(' => Token::LParen,
+                    ')' => Token::RParen,
+                    _ => Token::Comma,
+                }
+            }
+            _ => return error(&format!("unexpected '{}'", ch), start),
+        };
+        tokens.push((token, start));
+    }
+    Ok(tokens)
+}
+
+// binding powers, higher binds tighter
+// a left power below the right one makes an operator left-associative, above makes it right-associative
+fn infix_power(op: char) -> (u8, u8, Op) {
+    match op {
+        '+' => (1, 2, Op::Add),
+        '-' => (1, 2, Op::Sub),
+        '*' => (3, 4, Op::Mul),
+        '/' => (3, 4, Op::Div),
+        _ => (8, 7, Op::Pow),
+    }
+}
+
+// unary minus binds looser than ^, so -x^2 is -(x^2)
+const PREFIX_POWER: u8 = 5;
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    // where the query ends, for errors at the end of it
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens.get(self.pos).map_or(self.end, |&(_, position)| position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token, message: &str) -> Result<(), ParseError> {
+        match self.peek() {
+            Some(token) if *token == expected => {
+                self.pos += 1;
+                Ok(())
+            }
+            _ => error(message, self.position()),
+        }
+    }
+
+    fn expr(&mut self, min_power: u8) -> Result<Ast, ParseError> {
+        let position = self.position();
+        let mut lhs = match self.next() {
+            Some(Token::Num(n)) => Ast::Num(n),
+            Some(Token::Ident(name)) if self.peek() == Some(&Token::LParen) => {
+                self.pos += 1;
+                Ast::Call(name, self.args()?)
+            }
+            Some(Token::Ident(name)) => Ast::Var(name),
+            Some(Token::Op('-')) => Ast::Neg(Box::new(self.expr(PREFIX_POWER)?)),
+            Some(Token::Op('+')) => self.expr(PREFIX_POWER)?,
+            Some(Token::LParen) => {
+                let inner = self.expr(0)?;
+                self.expect(Token::RParen, "expected ')'")?;
+                inner
+            }
+            Some(_) => return error("expected a number, variable or '('", position),
+            None => return error("unexpected end of query", position),
+        };
+
+        loop {
+            let (left, right, op) = match self.peek() {
+                Some(Token::Op(op)) => infix_power(*op),
+                // anything straight before a variable, call or bracket multiplies it, as in 2x, 3(x + 1) or x sin(x)
+                // it binds like *, so -2x is -2 * x, 1/2x is 1/2 * x and x^2x is x^2 * x
+                Some(Token::Ident(_)) | Some(Token::LParen) => infix_power('*'),
+                _ => break,
+            };
+            if left < min_power {
+                break;
+            }
+            if matches!(self.peek(), Some(Token::Op(_))) {
+                self.pos += 1;
+            }
+            let rhs = self.expr(right)?;
+            lhs = Ast::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    // comma separated arguments, after the opening bracket
+    fn args(&mut self) -> Result<Vec<Ast>, ParseError> {
+        let mut args = Vec::new();
+        if self.peek() == Some(&Token::RParen) {
+            self.pos += 1;
+            return Ok(args);
+        }
+        loop {
+            args.push(self.expr(0)?);
+            match self.next() {
+                Some(Token::Comma) => continue,
+                Some(Token::RParen) => return Ok(args),
+                _ => return error("expected ',' or ')'", self.position() - 1),
+            }
+        }
+    }
+}
+
+pub fn parse(query: &str) -> Result<Ast, ParseError> {
+    let mut parser = Parser {
+        tokens: tokenize(query)?,
+        pos: 0,
+        end: query.chars().count(),
+    };
+    let ast = parser.expr(0)?;
+    match parser.peek() {
+        None => Ok(ast),
+        Some(Token::RParen) => error("unmatched ')'", parser.position()),
+        Some(_) => error("expected an operator", parser.position()),
+    }
+}
+
+impl Op {
+    fn symbol(&self) -> &'static str {
+        match self {
+            Op::Add => " + ",
+            Op::Sub => " - ",
+            Op::Mul => " * ",
+            Op::Div => " / ",
+            Op::Pow => "^",
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Op::Add | Op::Sub => 1,
+            Op::Mul | Op::Div => 2,
+            Op::Pow => 4,
+        }
+    }
+}
+
+impl Ast {
+    fn precedence(&self) -> u8 {
+        match self {
+            Ast::Binary(op, _, _) => op.precedence(),
+            Ast::Neg(_) => 3,
+            Ast::Num(n) if *n < 0.0 => 3,
+            _ => 5,
+        }
+    }
+}
+
+// with only the brackets the precedence needs
+impl fmt::Display for Ast {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let bracket = |f: &mut fmt::Formatter, ast: &Ast, needed: bool| match needed {
+            true => write!(f, "({})", ast),
+            false => write!(f, "{}", ast),
+        };
+        match self {
+            Ast::Num(n) => write!(f, "{}", n),
+            Ast::Var(name) => write!(f, "{}", name),
+            Ast::Neg(inner) => {
+                write!(f, "-")?;
+                bracket(f, inner, inner.precedence() < 4)
+            }
+            Ast::Binary(op, lhs, rhs) => {
+                let p = op.precedence();
+                match op {
+                    // ^ groups to the right
+                    Op::Pow => {
+                        bracket(f, lhs, lhs.precedence() <= p)?;
+                        write!(f, "{}", op.symbol())?;
+                        bracket(f, rhs, rhs.precedence() < p)
+                    }
+                    // a - (b - c) and a / (b / c) need their brackets
+                    _ => {
+                        bracket(f, lhs, lhs.precedence() < p)?;
+                        write!(f, "{}", op.symbol())?;
+                        let grouped = matches!(op, Op::Sub | Op::Div) || matches!(**rhs, Ast::Binary(Op::Sub | Op::Div, _, _));
+                        bracket(f, rhs, rhs.precedence() < p || (rhs.precedence() == p && grouped))
+                    }
+                }
+            }
+            Ast::Call(name, args) => {
+                write!(f, "{}(", name)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
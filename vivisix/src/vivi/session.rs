@@ -0,0 +1,116 @@
+// an interactive session, keeping the variables bound and the lines entered so far
+// EX: "x = 3" then "x^2 + 1" -> "x^2 + 1 = 10"
+use crate::vivi::eval::Env;
+use crate::vivi::parse::is_identifier;
+use crate::vivi::vivi::Vivi;
+
+// every line indented, the first one too, for the REPL and vivi --help alike
+pub const HELP: &str = "    QUERY                  simplify, and evaluate if every variable is bound
+    d/dVAR QUERY           differentiate with respect to VAR, as in d/dx x^2
+    simplify QUERY         simplify
+    eval QUERY             evaluate with the variables bound so far
+    NAME = QUERY           bind NAME to the value of QUERY
+    !!, !N                 run the last line again, or line N
+    :vars                  list the bound variables
+    :history               list the lines entered so far
+    :help                  print this
+    :quit                  leave";
+
+#[derive(Default)]
+pub struct Session {
+    vivi: Vivi,
+    env: Env,
+    history: Vec<String>,
+}
+
+// the variable in d/dx, d/dt and so on
+pub fn derivative_variable(op: &str) -> Option<&str> {
+    op.strip_prefix("d/d").filter(|var| is_identifier(var))
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn env(&self) -> &Env {
+        &self.env
+    }
+
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    // the reply to one line of input
+    pub fn run(&mut self, line: &str) -> Result<String, String> {
+        let line = self.recall(line.trim())?;
+        if let Some(command) = line.strip_prefix(':') {
+            return self.command(command.trim());
+        }
+        self.history.push(line.clone());
+
+        let (op, rest) = match line.split_once(char::is_whitespace) {
+            Some((op, rest)) => (op, rest.trim()),
+            None => (line.as_str(), ""),
+        };
+        if let Some(var) = derivative_variable(op) {
+            return Ok(self.vivi.differentiate(rest, var)?.to_string());
+        }
+        match op {
+            "simplify" => Ok(self.vivi.simplify(rest)?.to_string()),
+            "eval" => Ok(self.vivi.evaluate(rest, &self.env)?.to_string()),
+            _ => match line.split_once('=') {
+                Some((name, query)) => self.assign(name.trim(), query),
+                None => self.query(&line),
+            },
+        }
+    }
+
+    // !! and !N stand for lines from the history
+    fn recall(&self, line: &str) -> Result<String, String> {
+        let n = match line.strip_prefix('!') {
+            Some("!") => self.history.len(),
+            Some(n) => n.parse().map_err(|_| format!("expected !! or !N, not {}", line))?,
+            None => return Ok(line.to_string()),
+        };
+        match n.checked_sub(1).and_then(|i| self.history.get(i)) {
+            Some(recalled) => Ok(recalled.clone()),
+            None => Err(format!("no line {} in the history", n)),
+        }
+    }
+
+    fn command(&self, command: &str) -> Result<String, String> {
+        match command {
+            "vars" => Ok(match self.env.vars().as_slice() {
+                [] => "no variables bound".to_string(),
+                vars => vars.iter().map(|(name, value)| format!("{} = {}", name, value)).collect::<Vec<_>>().join("\n"),
+            }),
+            "history" => Ok(self
+                .history
+                .iter()
+                .enumerate()
+                .map(|(i, line)| format!("{:>4}  {}", i + 1, line))
+                .collect::<Vec<_>>()
+                .join("\n")),
+            "help" => Ok(HELP.to_string()),
+            _ => Err(format!("unknown command :{}, try :help", command)),
+        }
+    }
+
+    fn assign(&mut self, name: &str, query: &str) -> Result<String, String> {
+        if !is_identifier(name) {
+            return Err(format!("can't assign to {}", name));
+        }
+        let value = self.vivi.evaluate(query, &self.env)?;
+        self.env.set(name, value);
+        Ok(format!("{} = {}", name, value))
+    }
+
+    fn query(&self, query: &str) -> Result<String, String> {
+        let simplified = self.vivi.simplify(query)?;
+        match self.vivi.evaluate(query, &self.env) {
+            Ok(value) if simplified.to_string() != value.to_string() => Ok(format!("{} = {}", simplified, value)),
+            _ => Ok(simplified.to_string()),
+        }
+    }
+}
@@ -0,0 +1,229 @@
+// algebraic simplification into a normal form
+// sums and products are flattened, like terms and like factors collected, and both kept in a fixed order
+// EX: x * 2 + y * x^2 / x + 3 * x -> x * y + 5 * x
+use crate::vivi::expr::{singular, Expr};
+use std::cmp::Ordering;
+
+// a coefficient times bases raised to exponents, one entry per base, sorted by base
+#[derive(Debug, Clone, PartialEq)]
+struct Product {
+    coef: f64,
+    factors: Vec<(Expr, Expr)>,
+}
+
+impl Product {
+    fn number(coef: f64) -> Product {
+        Product { coef, factors: vec![] }
+    }
+
+    fn factor(base: Expr, exponent: Expr) -> Product {
+        Product {
+            coef: 1.,
+            factors: vec![(base, exponent)],
+        }
+    }
+
+    fn negate(mut self) -> Product {
+        self.coef = -self.coef;
+        self
+    }
+
+    // like factors merge by adding their exponents, x^2 * x^-1 -> x
+    fn merge(mut self, other: Product) -> Product {
+        self.coef *= other.coef;
+        for (base, exponent) in other.factors {
+            match self.factors.iter().position(|(b, _)| *b == base) {
+                Some(i) => {
+                    let (_, e) = self.factors.remove(i);
+                    let merged = Expr::Add(Box::new(e), Box::new(exponent)).simplify();
+                    if merged != Expr::Num(0.) {
+                        self.factors.push((base, merged));
+                    }
+                }
+                None => self.factors.push((base, exponent)),
+            }
+        }
+        self.factors.sort_by_key(|(base, _)| key(base));
+        self
+    }
+
+    // (c * x^a)^n -> c^n * x^(a * n), which holds for whole n
+    fn power(self, n: f64) -> Product {
+        let coef = self.coef.powf(n);
+        if !coef.is_finite() {
+            return Product::factor(build(self), Expr::Num(n));
+        }
+        Product {
+            coef,
+            factors: self
+                .factors
+                .into_iter()
+                .map(|(base, e)| (base, Expr::Mul(Box::new(e), Box::new(Expr::Num(n))).simplify()))
+                .collect(),
+        }
+    }
+
+    // a zero coefficient only zeroes the product if no factor is like 0^-1
+    fn is_zero(&self) -> bool {
+        self.coef == 0. && !self.factors.iter().any(|(b, e)| singular(b, e))
+    }
+
+    // total numeric power, what orders the terms of a sum
+    fn degree(&self) -> f64 {
+        self.factors
+            .iter()
+            .map(|(_, e)| match e {
+                Expr::Num(n) => *n,
+                _ => 0.,
+            })
+            .sum()
+    }
+}
+
+impl Expr {
+    // the same expression in reduced normal form
+    pub fn simplify(&self) -> Expr {
+        match self {
+            Expr::Num(_) | Expr::Var(_) => self.clone(),
+            Expr::Add(a1, a2) => {
+                let mut all = terms(&a1.simplify());
+                all.extend(terms(&a2.simplify()));
+                sum(all)
+            }
+            Expr::Neg(e) => sum(terms(&e.simplify()).into_iter().map(Product::negate).collect()),
+            Expr::Mul(m1, m2) => build(product(&m1.simplify()).merge(product(&m2.simplify()))),
+            Expr::Pow(b, e) => match (b.simplify(), e.simplify()) {
+                (b, Expr::Num(n)) if n.fract() == 0. => build(product(&b).power(n)),
+                (b, e) => Expr::make_power(b, e),
+            },
+            Expr::Call(name, args) => call(name, args.iter().map(Expr::simplify).collect()),
+        }
+    }
+}
+
+// the terms of a simplified expression
+fn terms(e: &Expr) -> Vec<Product> {
+    match e {
+        Expr::Add(a1, a2) => {
+            let mut all = terms(a1);
+            all.extend(terms(a2));
+            all
+        }
+        Expr::Neg(e) => terms(e).into_iter().map(Product::negate).collect(),
+        _ => vec![product(e)],
+    }
+}
+
+// the factors of a simplified expression
+fn product(e: &Expr) -> Product {
+    match e {
+        Expr::Num(n) => Product::number(*n),
+        Expr::Neg(e) => product(e).negate(),
+        Expr::Mul(m1, m2) => product(m1).merge(product(m2)),
+        Expr::Pow(b, n) => match **n {
+            Expr::Num(n) if n.fract() == 0. => product(b).power(n),
+            _ => Product::factor((**b).clone(), (**n).clone()),
+        },
+        _ => Product::factor(e.clone(), Expr::Num(1.)),
+    }
+}
+
+// like terms are collected by adding their coefficients, 2 * x + x -> 3 * x
+fn sum(terms: Vec<Product>) -> Expr {
+    let mut collected: Vec<Product> = vec![];
+    for term in terms {
+        match collected.iter_mut().find(|t| t.factors == term.factors) {
+            Some(t) => t.coef += term.coef,
+            None => collected.push(term),
+        }
+    }
+    collected.retain(|t| !t.is_zero());
+    collected.sort_by(order);
+
+    let mut terms = collected.into_iter();
+    let first = match terms.next() {
+        Some(term) => build(term),
+        None => return Expr::Num(0.),
+    };
+    terms.fold(first, |sum, term| match term.coef < 0. {
+        true => Expr::Add(Box::new(sum), Box::new(Expr::Neg(Box::new(build(term.negate()))))),
+        false => Expr::Add(Box::new(sum), Box::new(build(term))),
+    })
+}
+
+// c * numerator / denominator, where the denominator takes every negative power
+fn build(p: Product) -> Expr {
+    if p.is_zero() {
+        return Expr::Num(0.);
+    }
+    let power = |base: Expr, e: Expr| match e {
+        Expr::Num(1.) => base,
+        e => Expr::Pow(Box::new(base), Box::new(e)),
+    };
+    let mut numerator = vec![];
+    let mut denominator = vec![];
+    for (base, e) in p.factors {
+        match e {
+            Expr::Num(n) if n < 0. => denominator.push(power(base, Expr::Num(-n))),
+            e => numerator.push(power(base, e)),
+        }
+    }
+
+    let body = match (p.coef, numerator.is_empty()) {
+        (c, true) if c == 1. && !denominator.is_empty() => None,
+        (c, true) => Some(Expr::Num(c)),
+        (1., false) => Some(chain(numerator)),
+        (-1., false) => Some(Expr::Neg(Box::new(chain(numerator)))),
+        (c, false) => Some(chain(std::iter::once(Expr::Num(c)).chain(numerator).collect())),
+    };
+    if denominator.is_empty() {
+        return body.unwrap_or(Expr::Num(1.));
+    }
+    let reciprocal = Expr::Pow(Box::new(chain(denominator)), Box::new(Expr::Num(-1.)));
+    match body {
+        Some(body) => Expr::Mul(Box::new(body), Box::new(reciprocal)),
+        None => reciprocal,
+    }
+}
+
+fn chain(factors: Vec<Expr>) -> Expr {
+    factors
+        .into_iter()
+        .reduce(|product, factor| Expr::Mul(Box::new(product), Box::new(factor)))
+        .unwrap_or(Expr::Num(1.))
+}
+
+// the identities that stay exact, sin(0) -> 0 but sin(1) stays
+fn call(name: &str, args: Vec<Expr>) -> Expr {
+    match (name, args.as_slice()) {
+        ("sin", [Expr::Num(n)]) | ("tan", [Expr::Num(n)]) if *n == 0. => Expr::Num(0.),
+        ("cos", [Expr::Num(n)]) | ("exp", [Expr::Num(n)]) if *n == 0. => Expr::Num(1.),
+        ("ln", [Expr::Num(n)]) if *n == 1. => Expr::Num(0.),
+        ("sqrt", [Expr::Num(n)]) if *n >= 0. && n.sqrt().fract() == 0. => Expr::Num(n.sqrt()),
+        ("ln", [Expr::Call(inner, args)]) if inner == "exp" && args.len() == 1 => args[0].clone(),
+        _ => Expr::make_call(name, args),
+    }
+}
+
+// variables first, then bracketed expressions, then functions, each alphabetically
+fn key(e: &Expr) -> (u8, String) {
+    let rank = match e {
+        Expr::Var(_) => 0,
+        Expr::Num(_) => 1,
+        Expr::Add(_, _) | Expr::Mul(_, _) | Expr::Neg(_) | Expr::Pow(_, _) => 2,
+        Expr::Call(_, _) => 3,
+    };
+    (rank, e.to_string())
+}
+
+// highest degree first and constants last, x^2 + 2 * x + 1
+fn order(t1: &Product, t2: &Product) -> Ordering {
+    let factors = |t: &Product| -> Vec<((u8, String), String)> {
+        t.factors.iter().map(|(b, e)| (key(b), e.to_string())).collect()
+    };
+    t2.degree()
+        .partial_cmp(&t1.degree())
+        .unwrap_or(Ordering::Equal)
+        .then_with(|| t1.factors.is_empty().cmp(&t2.factors.is_empty()))
+        .then_with(|| factors(t1).cmp(&factors(t2)))
+}